@@ -11,6 +11,13 @@ use regex::Regex;
 use yaml_rust2::Yaml;
 use yaml_rust2::YamlLoader;
 
+use crate::font::CodepointCollector;
+use crate::font::Font;
+use crate::font::FontOptions;
+use crate::font::RenderMode;
+use crate::font::SizeMode;
+use crate::util::CrcHelper;
+
 #[derive(Clone, Hash, Eq, PartialEq, Debug)]
 enum ParameterValue {
 	NoValue,
@@ -34,6 +41,7 @@ struct ToolRun {
 	command:    String,
 	output:     String,
 	input:      Vec<String>,
+	text:       Vec<String>,
 	parameters: HashMap<String, ParameterValue>,
 	cmd_line:   String,
 }
@@ -43,7 +51,8 @@ impl ToolRun {
 		tool: &str,
 		command: &str,
 		output: &str,
-		input: &Vec<String>,
+		input: &[String],
+		text: &[String],
 		parameters: &HashMap<String, ParameterValue>,
 		cmd_line: &str,
 	) -> ToolRun {
@@ -51,11 +60,26 @@ impl ToolRun {
 			tool:       tool.to_string(),
 			command:    command.to_string(),
 			output:     output.to_string(),
-			input:      input.clone(),
+			input:      input.to_vec(),
+			text:       text.to_vec(),
 			parameters: parameters.clone(),
 			cmd_line:   cmd_line.to_string(),
 		}
 	}
+	fn parameter_u32(&self, name: &str) -> Option<u32> {
+		match self.parameters.get(name) {
+			Some(ParameterValue::IntegerValue(i)) => Some(*i as u32),
+			Some(ParameterValue::StringValue(s)) => s.parse().ok(),
+			_ => None,
+		}
+	}
+	fn parameter_string(&self, name: &str) -> Option<String> {
+		match self.parameters.get(name) {
+			Some(ParameterValue::StringValue(s)) => Some(s.clone()),
+			Some(ParameterValue::IntegerValue(i)) => Some(i.to_string()),
+			_ => None,
+		}
+	}
 	fn run(&self, asset_builder: &AssetBuilder) -> Result<u32, &'static str> {
 		let tool = self.tool.as_str();
 		match tool {
@@ -145,6 +169,7 @@ impl AssetBuilder {
 				}
 				Ok(number_of_assets_updated)
 			},
+			"font" => self.tool_asset_font(tool_run),
 			cmd => {
 				println!("Unhandled asset tool command: {:?}", cmd);
 				Err("Unhandled asset tool command")
@@ -152,6 +177,131 @@ impl AssetBuilder {
 		}
 	}
 
+	// true if the output exists, and is newer than all inputs
+	fn is_up_to_date(output: &Path, inputs: &[String]) -> bool {
+		let output_modified = match fs::metadata(output).and_then(|m| m.modified()) {
+			Ok(m) => m,
+			Err(_) => return false,
+		};
+		inputs.iter().all(|i| {
+			fs::metadata(i)
+				.and_then(|m| m.modified())
+				.map(|m| m <= output_modified)
+				.unwrap_or(false)
+		})
+	}
+
+	// from the tool parameters, with the codepoints of the text files
+	fn font_options(tool_run: &ToolRun) -> Result<FontOptions, &'static str> {
+		let mut options = FontOptions::default();
+		if let Some(size) = tool_run.parameter_u32("size") {
+			options = options.with_size(size);
+		}
+		if let Some(size_mode) = tool_run.parameter_string("size-mode") {
+			match size_mode.parse::<SizeMode>() {
+				Ok(size_mode) => options = options.with_size_mode(size_mode),
+				Err(e) => {
+					println!("🔤 ‼️ Error: {}", e);
+					return Err("Unknown font size mode");
+				},
+			}
+		}
+		if let Some(face_index) = tool_run.parameter_u32("face-index") {
+			options = options.with_face_index(face_index);
+		}
+		if let Some(variations) = tool_run.parameter_string("variations") {
+			match FontOptions::parse_variations(&variations) {
+				Ok(variations) => options = options.with_variations(variations),
+				Err(e) => {
+					println!("🔤 ‼️ Error: {}", e);
+					return Err("Invalid font variations");
+				},
+			}
+		}
+		if let Some(texsize) = tool_run.parameter_u32("texsize") {
+			options = options.with_texsize(texsize);
+		}
+		if let Some(maximum_texsize) = tool_run.parameter_u32("maximum-texsize") {
+			options = options.with_maximum_texsize(maximum_texsize);
+		}
+		if let Some(border) = tool_run.parameter_u32("border") {
+			options = options.with_border(border);
+		}
+		if let Some(mode) = tool_run.parameter_string("mode") {
+			match mode.parse::<RenderMode>() {
+				Ok(mode) => options = options.with_render_mode(mode),
+				Err(e) => {
+					println!("🔤 ‼️ Error: {}", e);
+					return Err("Unknown font render mode");
+				},
+			}
+		}
+		if !tool_run.text.is_empty() {
+			let mut collector = CodepointCollector::default();
+			for t in &tool_run.text {
+				if let Err(e) = collector.add_file(Path::new(t)) {
+					println!("🔤 ‼️ Error: {}", e);
+					return Err("Error collecting characters for font");
+				}
+			}
+			println!(
+				"🔤 Found {} different characters in {} text files",
+				collector.len(),
+				tool_run.text.len()
+			);
+			options = options.with_codepoints(collector.codepoints());
+		}
+		Ok(options)
+	}
+
+	// creates a font from the input fonts, with only the glyphs used in the text files (if any)
+	// rebuilds only when the fonts, the text files, or the options changed
+	fn tool_asset_font(&self, tool_run: &ToolRun) -> Result<u32, &'static str> {
+		let output = self.replace_placeholders(tool_run, &tool_run.output);
+		let dest = format!("{}/{}", self.data_directory, output);
+		let omfont = format!("{}.omfont", dest);
+
+		let options = AssetBuilder::font_options(tool_run)?;
+		// the options, including the codepoints, are kept next to the font to rebuild when they change
+		let options_file = format!("{}.options", omfont);
+		let options_hash = format!(
+			"{:08x}\n",
+			CrcHelper::crc_from_data(format!("{:?}", options).as_bytes())
+		);
+
+		let mut sources = tool_run.input.clone();
+		sources.extend(tool_run.text.iter().cloned());
+		if AssetBuilder::is_up_to_date(Path::new(&omfont), &sources)
+			&& fs::read_to_string(&options_file).ok().as_ref() == Some(&options_hash)
+		{
+			println!("🔤 ✅ {:?} is up to date", &omfont);
+			return Ok(0);
+		}
+		if self.dry_run {
+			println!(
+				"🌵 Dry Run: Would create font {:?} from {:?} for {:?}",
+				&dest, &tool_run.input, &tool_run.text
+			);
+			return Ok(0);
+		}
+
+		let input: Vec<&str> = tool_run.input.iter().map(String::as_str).collect();
+		match Font::create_with_options(&dest, &options, &input) {
+			Ok(_) => {
+				println!("🔤 ✅ Created font {:?}", &dest);
+				if let Err(e) = fs::write(&options_file, &options_hash) {
+					println!("🔤 ‼️ Error: Writing {:?}: {}", &options_file, e);
+					return Err("Error writing font options");
+				}
+				Ok(1)
+			},
+			Err(e) => {
+				println!("🔤 ‼️ Error: Creating font {:?}: {}", &dest, e);
+				Err("Error creating font")
+			},
+		}
+	}
+
 	fn replace_placeholders(&self, tool_run: &ToolRun, input: &str) -> String {
 		let output = input.clone();
 		//		let re = Regex::new(r"\$\{((.*?)(\s*)(.*)?)\}").unwrap();
@@ -182,6 +332,7 @@ impl AssetBuilder {
 							.join(" ")
 							.to_string()
 					},
+					"text" => tool_run.text.join(" "),
 					"input:basename" => {
 						let input = if tool_run.input.len() > 0 {
 							tool_run.input[0].clone()
@@ -340,6 +491,36 @@ impl AssetBuilder {
 					};
 				}
 
+				// text files, e.g. localization tables, used by fonts
+				let mut text = Vec::new();
+				match doc["text"].as_vec() {
+					Some(t) => {
+						for t in t {
+							if let Some(s) = t.as_str() {
+								text.push(s.to_string());
+							}
+						}
+					},
+					None => {
+						if let Some(t) = doc["text"].as_str() {
+							text.push(t.to_string());
+						}
+					},
+				}
+				let text = text
+					.iter()
+					.map(|t| format!("{}/{}", asset_path.display(), t))
+					.collect::<Vec<_>>();
+				let mut expanded_text = Vec::new();
+				for t in text.iter() {
+					for exp in glob(t).expect("Failed glob pattern") {
+						match exp.map(|e| e.into_os_string().into_string()) {
+							Ok(Ok(e)) => expanded_text.push(e),
+							_ => return Err("Error globing text"),
+						}
+					}
+				}
+
 				//				println!("INPUT {:?}", input );
 				let _input_original = input.iter().map(|i| format!("{}", i)).collect::<Vec<_>>();
 				let input = input
@@ -415,6 +596,7 @@ impl AssetBuilder {
 						&command,
 						&output,
 						&expanded_input,
+						&expanded_text,
 						&parameters,
 						&cmd_line,
 					);
@@ -434,6 +616,7 @@ impl AssetBuilder {
 							&command,
 							&output,
 							&single_input,
+							&expanded_text,
 							&parameters,
 							&cmd_line,
 						);
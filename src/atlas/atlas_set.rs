@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
@@ -6,6 +7,7 @@ use std::path::PathBuf;
 use crate::atlas::Atlas;
 use crate::atlas::AtlasEntry;
 use crate::atlas::AtlasFitter;
+use crate::font::Font;
 
 #[derive(Debug, Default)]
 pub struct AtlasSet {
@@ -15,7 +17,9 @@ pub struct AtlasSet {
 	maximum_size:   Option<u32>,
 	reference_path: Option<PathBuf>,
 	inputs:         Vec<PathBuf>,
+	fonts:          Vec<PathBuf>,
 	atlases:        Vec<Atlas>,
+	placed_fonts:   Vec<(String, Font)>,
 }
 
 impl AtlasSet {
@@ -41,6 +45,36 @@ impl AtlasSet {
 			entries.push(e);
 		}
 
+		// glyph images of fonts created with `omt-font create --glyphs-only`
+		let mut fonts = Vec::new();
+		let mut glyph_entries = HashMap::new();
+		for (font_index, f) in self.fonts.iter().enumerate() {
+			let name = match f.to_str() {
+				Some(s) => s.to_string(),
+				None => anyhow::bail!("Error converting path to string"),
+			};
+			println!("Analysing font {:?}", f);
+			let (font, page_count) = Font::load_metrics(&name)?;
+			if page_count != 0 {
+				anyhow::bail!(
+					"Font {} already has a texture, create it with `omt-font create --glyphs-only`",
+					name
+				);
+			}
+			for (glyph_index, g) in font.glyphs.iter().enumerate() {
+				if g.width == 0 || g.height == 0 {
+					continue;
+				}
+				let filename = Font::glyph_image_filename(&name, g.codepoint);
+				let img = image::open(&filename)?;
+				let mut e = AtlasEntry::new(&filename, 0, 0);
+				e.set_image(img);
+				entries.push(e);
+				glyph_entries.insert(filename, (font_index, glyph_index));
+			}
+			fonts.push((name, font));
+		}
+
 		// sort entries by size
 		entries.sort_by(
 			|a, b| b.height.cmp(&a.height), // higher ones first
@@ -73,6 +107,12 @@ impl AtlasSet {
 				let mut entry = entry.clone();
 				entry.set_position(e.x, e.y);
 				println!("{:#?}", entry);
+				if let Some((font_index, glyph_index)) = glyph_entries.get(&entry.filename) {
+					let glyph = &mut fonts[*font_index].1.glyphs[*glyph_index];
+					glyph.x = e.x;
+					glyph.y = e.y;
+					glyph.page = atlases.len() as u16;
+				}
 				a.add_entry(entry);
 			}
 			//a.blit_entries(); // defer blitting
@@ -80,6 +120,7 @@ impl AtlasSet {
 		}
 
 		self.atlases = atlases;
+		self.placed_fonts = fonts;
 		Ok(self.atlases.len() as u32)
 	}
 
@@ -120,6 +161,17 @@ impl AtlasSet {
 			Some(s) => s,
 			None => anyhow::bail!("Error converting path to string"),
 		};
+		let glyph_filenames: HashSet<String> = self
+			.placed_fonts
+			.iter()
+			.flat_map(|(name, font)| {
+				font.glyphs
+					.iter()
+					.map(|g| Font::glyph_image_filename(name, g.codepoint))
+					.collect::<Vec<_>>()
+			})
+			.collect();
+		let mut atlas_stems = Vec::new();
 		for a in self.atlases.iter() {
 			//			println!("Atlas #{} {:?}", n, a );
 			let outname = crate::atlas::atlas::simple_format_u32(&output_string, n); //format!(output, n);
@@ -161,7 +213,12 @@ impl AtlasSet {
 					.unwrap()
 					.to_string();
 				println!("Writing references for {} to {}", &atlas_stem, rp.display());
+				atlas_stems.push(atlas_stem.clone());
 				for e in a.entries.iter() {
+					// glyphs are referenced via their font
+					if glyph_filenames.contains(&e.filename) {
+						continue;
+					}
 					//dbg!(&e);
 					let stem = e.get_stem();
 					//println!("{} in {}", &stem, &atlas_stem);
@@ -180,6 +237,23 @@ impl AtlasSet {
 			n += 1;
 		}
 
+		// point the fonts to the atlas pages their glyphs ended up on
+		let size = self.target_size.unwrap_or(0);
+		for (name, font) in self.placed_fonts.iter_mut() {
+			font.save_in_atlas(name, size, n)?;
+			if let Some(rp) = &reference_path {
+				let mut omtr = PathBuf::new();
+				omtr.push(rp);
+				omtr.push(Path::new(name.as_str()).file_name().unwrap());
+				omtr.set_extension("omtr");
+				let mut f = File::create(omtr)?;
+				// one line per page
+				for atlas_stem in &atlas_stems {
+					writeln!(f, "{}", atlas_stem)?;
+				}
+			}
+		}
+
 		Ok(n)
 	}
 	pub fn with_output(mut self, output: &Path) -> Self {
@@ -208,6 +282,13 @@ impl AtlasSet {
 		}
 		self
 	}
+	// fonts are given by name, without the .omfont extension
+	pub fn with_fonts(mut self, fonts: Vec<&Path>) -> Self {
+		for font in fonts.iter() {
+			self.fonts.push(font.to_path_buf());
+		}
+		self
+	}
 	pub fn with_maximum_size(mut self, maximum_size: u32) -> Self {
 		self.maximum_size = Some(maximum_size);
 		self
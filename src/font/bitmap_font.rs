@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+// a single glyph of a bitmap font, one byte of coverage (0 or 255) per pixel
+#[derive(Debug, Clone, Default)]
+pub struct BitmapGlyph {
+	pub width:    u32,
+	pub height:   u32,
+	pub x_offset: i32, // left bearing
+	pub y_offset: i32, // baseline to bottom row, positive is up (BDF convention)
+	pub advance:  i32,
+	pub pixels:   Vec<u8>,
+}
+
+impl BitmapGlyph {
+	pub fn get(&self, x: u32, y: u32) -> u8 {
+		if x >= self.width || y >= self.height {
+			0
+		} else {
+			self.pixels[(y * self.width + x) as usize]
+		}
+	}
+}
+
+// pixel font loaded from a .bdf (text), or .pcf (binary) file
+#[derive(Debug, Default)]
+pub struct BitmapFont {
+	pub pixel_size: u32,
+	pub ascent:     i32,
+	pub descent:    i32,
+	pub glyphs:     HashMap<u32, BitmapGlyph>,
+}
+
+impl BitmapFont {
+	pub fn is_bitmap_font(filename: &str) -> bool {
+		let ext = Path::new(filename)
+			.extension()
+			.and_then(|e| e.to_str())
+			.unwrap_or("")
+			.to_lowercase();
+		ext == "bdf" || ext == "pcf"
+	}
+
+	pub fn load(filename: &str) -> anyhow::Result<BitmapFont> {
+		let data = match fs::read(filename) {
+			Ok(d) => d,
+			Err(e) => anyhow::bail!("Error reading {}: {}", filename, e),
+		};
+
+		if data.starts_with(b"\x01fcp") {
+			BitmapFont::load_pcf(&data)
+		} else if data.starts_with(b"STARTFONT") {
+			BitmapFont::load_bdf(&data)
+		} else {
+			anyhow::bail!("{} is neither a BDF, nor a PCF font", filename);
+		}
+	}
+
+	pub fn glyph(&self, codepoint: u32) -> Option<&BitmapGlyph> {
+		self.glyphs.get(&codepoint)
+	}
+
+	fn load_bdf(data: &[u8]) -> anyhow::Result<BitmapFont> {
+		let text = String::from_utf8_lossy(data);
+		let mut font = BitmapFont::default();
+
+		let mut lines = text.lines();
+		while let Some(line) = lines.next() {
+			let mut parts = line.split_whitespace();
+			let keyword = parts.next().unwrap_or("");
+			let args: Vec<&str> = parts.collect();
+			match keyword {
+				"SIZE" => {
+					font.pixel_size = parse_bdf_arg(&args, 0, line)? as u32;
+				},
+				"PIXEL_SIZE" => {
+					font.pixel_size = parse_bdf_arg(&args, 0, line)? as u32;
+				},
+				"FONT_ASCENT" => {
+					font.ascent = parse_bdf_arg(&args, 0, line)?;
+				},
+				"FONT_DESCENT" => {
+					font.descent = parse_bdf_arg(&args, 0, line)?;
+				},
+				"STARTCHAR" => {
+					let (codepoint, glyph) = BitmapFont::parse_bdf_char(&mut lines)?;
+					if let Some(codepoint) = codepoint {
+						font.glyphs.insert(codepoint, glyph);
+					}
+				},
+				_ => {},
+			}
+		}
+
+		if font.ascent + font.descent > 0 {
+			// prefer the actual line height over the nominal point size
+			font.pixel_size = (font.ascent + font.descent) as u32;
+		}
+
+		Ok(font)
+	}
+
+	fn parse_bdf_char<'a>(
+		lines: &mut impl Iterator<Item = &'a str>,
+	) -> anyhow::Result<(Option<u32>, BitmapGlyph)> {
+		let mut codepoint = None;
+		let mut glyph = BitmapGlyph::default();
+
+		while let Some(line) = lines.next() {
+			let mut parts = line.split_whitespace();
+			let keyword = parts.next().unwrap_or("");
+			let args: Vec<&str> = parts.collect();
+			match keyword {
+				"ENCODING" => {
+					let e = parse_bdf_arg(&args, 0, line)?;
+					// -1 means "not in the standard encoding"
+					if e >= 0 {
+						codepoint = Some(e as u32);
+					}
+				},
+				"DWIDTH" => {
+					glyph.advance = parse_bdf_arg(&args, 0, line)?;
+				},
+				"BBX" => {
+					glyph.width = parse_bdf_arg(&args, 0, line)? as u32;
+					glyph.height = parse_bdf_arg(&args, 1, line)? as u32;
+					glyph.x_offset = parse_bdf_arg(&args, 2, line)?;
+					glyph.y_offset = parse_bdf_arg(&args, 3, line)?;
+				},
+				"BITMAP" => {
+					glyph.pixels = vec![0; (glyph.width * glyph.height) as usize];
+					for y in 0..glyph.height {
+						let row = match lines.next() {
+							Some(r) => r.trim(),
+							None => anyhow::bail!("Unexpected end of BDF in BITMAP"),
+						};
+						for x in 0..glyph.width {
+							let nibble_index = (x / 4) as usize;
+							let nibble = match row.get(nibble_index..nibble_index + 1) {
+								Some(n) => u8::from_str_radix(n, 16)?,
+								None => 0,
+							};
+							if nibble & (0x8 >> (x % 4)) != 0 {
+								glyph.pixels[(y * glyph.width + x) as usize] = 255;
+							}
+						}
+					}
+				},
+				"ENDCHAR" => {
+					return Ok((codepoint, glyph));
+				},
+				_ => {},
+			}
+		}
+
+		anyhow::bail!("Unexpected end of BDF, missing ENDCHAR");
+	}
+
+	fn load_pcf(data: &[u8]) -> anyhow::Result<BitmapFont> {
+		const PCF_PROPERTIES: u32 = 1 << 0;
+		const PCF_METRICS: u32 = 1 << 2;
+		const PCF_BITMAPS: u32 = 1 << 3;
+		const PCF_BDF_ENCODINGS: u32 = 1 << 5;
+
+		let table_count = read_u32_le(data, 4)? as usize;
+		// each table of contents entry is 16 bytes
+		if table_count > data.len().saturating_sub(8) / 16 {
+			anyhow::bail!("PCF table count {} exceeds the file size", table_count);
+		}
+		let mut tables = HashMap::new();
+		for t in 0..table_count {
+			let base = 8 + t * 16;
+			let table_type = read_u32_le(data, base)?;
+			// format at base + 4 is repeated at the start of each table
+			let _size = read_u32_le(data, base + 8)?;
+			let offset = read_u32_le(data, base + 12)? as usize;
+			tables.insert(table_type, offset);
+		}
+
+		let metrics = match tables.get(&PCF_METRICS) {
+			Some(o) => PcfTable::new(data, *o)?.read_metrics()?,
+			None => anyhow::bail!("PCF without metrics table"),
+		};
+		let bitmaps = match tables.get(&PCF_BITMAPS) {
+			Some(o) => PcfTable::new(data, *o)?.read_bitmaps(&metrics)?,
+			None => anyhow::bail!("PCF without bitmaps table"),
+		};
+		let encodings = match tables.get(&PCF_BDF_ENCODINGS) {
+			Some(o) => PcfTable::new(data, *o)?.read_encodings()?,
+			None => anyhow::bail!("PCF without encodings table"),
+		};
+
+		let mut font = BitmapFont::default();
+		if let Some(o) = tables.get(&PCF_PROPERTIES) {
+			let properties = PcfTable::new(data, *o)?.read_properties()?;
+			font.pixel_size = *properties.get("PIXEL_SIZE").unwrap_or(&0) as u32;
+			font.ascent = *properties.get("FONT_ASCENT").unwrap_or(&0);
+			font.descent = *properties.get("FONT_DESCENT").unwrap_or(&0);
+		}
+		if font.ascent + font.descent > 0 {
+			font.pixel_size = (font.ascent + font.descent) as u32;
+		}
+
+		for (codepoint, index) in encodings {
+			let (m, pixels) = match (metrics.get(index), bitmaps.get(index)) {
+				(Some(m), Some(b)) => (m, b),
+				_ => anyhow::bail!("PCF glyph index {} out of range", index),
+			};
+			let glyph = BitmapGlyph {
+				width:    m.width(),
+				height:   m.height(),
+				x_offset: m.left_bearing,
+				y_offset: -m.descent,
+				advance:  m.advance,
+				pixels:   pixels.clone(),
+			};
+			font.glyphs.insert(codepoint, glyph);
+		}
+
+		Ok(font)
+	}
+}
+
+fn parse_bdf_arg(args: &[&str], index: usize, line: &str) -> anyhow::Result<i32> {
+	match args.get(index).map(|a| a.parse::<i32>()) {
+		Some(Ok(v)) => Ok(v),
+		_ => anyhow::bail!("Broken BDF line: {}", line),
+	}
+}
+
+fn read_u32_le(data: &[u8], pos: usize) -> anyhow::Result<u32> {
+	match pos.checked_add(4).and_then(|end| data.get(pos..end)) {
+		Some(b) => Ok(LittleEndian::read_u32(b)),
+		None => anyhow::bail!("Truncated PCF at {}", pos),
+	}
+}
+
+#[derive(Debug)]
+struct PcfMetrics {
+	left_bearing:  i32,
+	right_bearing: i32,
+	advance:       i32,
+	ascent:        i32,
+	descent:       i32,
+}
+
+impl PcfMetrics {
+	fn width(&self) -> u32 {
+		(self.right_bearing - self.left_bearing).max(0) as u32
+	}
+	fn height(&self) -> u32 {
+		(self.ascent + self.descent).max(0) as u32
+	}
+}
+
+// cursor into a single PCF table, honouring the table's byte order
+struct PcfTable<'a> {
+	data:   &'a [u8],
+	pos:    usize,
+	format: u32,
+}
+
+impl<'a> PcfTable<'a> {
+	const PCF_BYTE_MASK: u32 = 1 << 2;
+	const PCF_BIT_MASK: u32 = 1 << 3;
+	const PCF_COMPRESSED_METRICS: u32 = 0x100;
+
+	fn new(data: &'a [u8], offset: usize) -> anyhow::Result<Self> {
+		let format = read_u32_le(data, offset)?;
+		Ok(Self {
+			data,
+			// read_u32_le already checked offset + 4
+			pos: offset + 4,
+			format,
+		})
+	}
+
+	fn remaining(&self) -> usize {
+		self.data.len().saturating_sub(self.pos)
+	}
+
+	fn msb_first(&self) -> bool {
+		self.format & Self::PCF_BYTE_MASK != 0
+	}
+
+	fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+		let end = self.pos.checked_add(n);
+		match end.and_then(|end| self.data.get(self.pos..end).map(|b| (end, b))) {
+			Some((end, b)) => {
+				self.pos = end;
+				Ok(b)
+			},
+			None => anyhow::bail!("Truncated PCF table at {}", self.pos),
+		}
+	}
+
+	fn u8(&mut self) -> anyhow::Result<u8> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn i16(&mut self) -> anyhow::Result<i16> {
+		let msb = self.msb_first();
+		let b = self.take(2)?;
+		Ok(if msb {
+			BigEndian::read_i16(b)
+		} else {
+			LittleEndian::read_i16(b)
+		})
+	}
+
+	fn i32(&mut self) -> anyhow::Result<i32> {
+		let msb = self.msb_first();
+		let b = self.take(4)?;
+		Ok(if msb {
+			BigEndian::read_i32(b)
+		} else {
+			LittleEndian::read_i32(b)
+		})
+	}
+
+	// a size or offset, which must not be negative
+	fn size(&mut self, what: &str) -> anyhow::Result<usize> {
+		let v = self.i32()?;
+		if v < 0 {
+			anyhow::bail!("Negative PCF {} {}", what, v);
+		}
+		Ok(v as usize)
+	}
+
+	// a count of entries of item_size bytes each, which must fit into the rest of the table
+	fn count(&mut self, v: i32, item_size: usize) -> anyhow::Result<usize> {
+		if v < 0 || v as usize > self.remaining() / item_size {
+			anyhow::bail!("PCF count {} exceeds the table at {}", v, self.pos);
+		}
+		Ok(v as usize)
+	}
+
+	fn read_metrics(&mut self) -> anyhow::Result<Vec<PcfMetrics>> {
+		let compressed = self.format & Self::PCF_COMPRESSED_METRICS != 0;
+		let count = if compressed {
+			let v = self.i16()? as i32;
+			self.count(v, 5)?
+		} else {
+			let v = self.i32()?;
+			self.count(v, 12)?
+		};
+		let mut metrics = Vec::with_capacity(count);
+		for _ in 0..count {
+			let m = if compressed {
+				PcfMetrics {
+					left_bearing:  self.u8()? as i32 - 0x80,
+					right_bearing: self.u8()? as i32 - 0x80,
+					advance:       self.u8()? as i32 - 0x80,
+					ascent:        self.u8()? as i32 - 0x80,
+					descent:       self.u8()? as i32 - 0x80,
+				}
+			} else {
+				let m = PcfMetrics {
+					left_bearing:  self.i16()? as i32,
+					right_bearing: self.i16()? as i32,
+					advance:       self.i16()? as i32,
+					ascent:        self.i16()? as i32,
+					descent:       self.i16()? as i32,
+				};
+				let _attributes = self.i16()?;
+				m
+			};
+			metrics.push(m);
+		}
+		Ok(metrics)
+	}
+
+	fn read_bitmaps(&mut self, metrics: &[PcfMetrics]) -> anyhow::Result<Vec<Vec<u8>>> {
+		let v = self.i32()?;
+		let count = self.count(v, 4)?;
+		let mut offsets = Vec::with_capacity(count);
+		for _ in 0..count {
+			offsets.push(self.size("bitmap offset")?);
+		}
+		let mut sizes = [0usize; 4];
+		for s in &mut sizes {
+			*s = self.size("bitmap size")?;
+		}
+		let pad = 1usize << (self.format & 3);
+		let scan_unit = 1usize << ((self.format >> 4) & 3);
+		let bit_msb = self.format & Self::PCF_BIT_MASK != 0;
+		let swap = scan_unit > 1 && self.msb_first() != bit_msb;
+		let bitmap_data = self.take(sizes[(self.format & 3) as usize])?;
+
+		let mut bitmaps = Vec::with_capacity(count);
+		for (i, offset) in offsets.iter().enumerate() {
+			let (w, h) = match metrics.get(i) {
+				Some(m) => (m.width() as usize, m.height() as usize),
+				None => anyhow::bail!("PCF bitmap {} without metrics", i),
+			};
+			let stride = w.div_ceil(8).div_ceil(pad) * pad;
+			// check the whole glyph before allocating its pixels
+			let end = h.checked_mul(stride).and_then(|n| n.checked_add(*offset));
+			let glyph_data = match end.and_then(|end| bitmap_data.get(*offset..end)) {
+				Some(g) => g,
+				None => anyhow::bail!("PCF bitmap {} out of range", i),
+			};
+			let mut pixels = vec![0u8; w * h];
+			for y in 0..h {
+				let mut row = glyph_data[y * stride..(y + 1) * stride].to_vec();
+				if swap {
+					for unit in row.chunks_mut(scan_unit) {
+						unit.reverse();
+					}
+				}
+				for x in 0..w {
+					let bit = if bit_msb { 7 - (x % 8) } else { x % 8 };
+					if row[x / 8] & (1 << bit) != 0 {
+						pixels[y * w + x] = 255;
+					}
+				}
+			}
+			bitmaps.push(pixels);
+		}
+		Ok(bitmaps)
+	}
+
+	fn read_encodings(&mut self) -> anyhow::Result<Vec<(u32, usize)>> {
+		let min_byte2 = self.i16()? as u32;
+		let max_byte2 = self.i16()? as u32;
+		let min_byte1 = self.i16()? as u32;
+		let max_byte1 = self.i16()? as u32;
+		let _default_char = self.i16()?;
+
+		let mut encodings = Vec::new();
+		for byte1 in min_byte1..=max_byte1 {
+			for byte2 in min_byte2..=max_byte2 {
+				let index = self.i16()? as u16;
+				if index != 0xffff {
+					encodings.push(((byte1 << 8) | byte2, index as usize));
+				}
+			}
+		}
+		Ok(encodings)
+	}
+
+	fn read_properties(&mut self) -> anyhow::Result<HashMap<String, i32>> {
+		let v = self.i32()?;
+		// name offset, is string flag, value
+		let count = self.count(v, 9)?;
+		let mut raw = Vec::with_capacity(count);
+		for _ in 0..count {
+			let name_offset = self.size("property name offset")?;
+			let is_string = self.u8()? != 0;
+			let value = self.i32()?;
+			raw.push((name_offset, is_string, value));
+		}
+		// properties are padded to 4 bytes
+		if count & 3 != 0 {
+			self.take(4 - (count & 3))?;
+		}
+		let strings_size = self.size("strings size")?;
+		let strings = self.take(strings_size)?;
+
+		let mut properties = HashMap::new();
+		for (name_offset, is_string, value) in raw {
+			if is_string {
+				continue;
+			}
+			let name = match strings.get(name_offset..) {
+				Some(s) => s.split(|b| *b == 0).next().unwrap_or(&[]),
+				None => continue,
+			};
+			properties.insert(String::from_utf8_lossy(name).to_string(), value);
+		}
+		Ok(properties)
+	}
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use yaml_rust2::{Yaml, YamlLoader};
+
+// Collects the codepoints used by the strings of localization tables,
+// so fonts only need to contain the glyphs that are actually used.
+// Only values are used for JSON, and YAML, keys are ignored.
+#[derive(Debug, Default)]
+pub struct CodepointCollector {
+	codepoints: BTreeSet<u32>,
+}
+
+impl CodepointCollector {
+	pub fn add_text(&mut self, text: &str) {
+		for c in text.chars() {
+			if !c.is_control() {
+				self.codepoints.insert(c as u32);
+			}
+		}
+	}
+
+	// .csv, .tsv, .json, .po, .pot, .yaml, .yml, everything else is used as plain text
+	pub fn add_file(&mut self, filename: &Path) -> anyhow::Result<()> {
+		let text = match std::fs::read_to_string(filename) {
+			Ok(text) => text,
+			Err(e) => anyhow::bail!("Error reading {}: {}", filename.display(), e),
+		};
+		let extension = filename
+			.extension()
+			.map(|e| e.to_string_lossy().to_lowercase())
+			.unwrap_or_default();
+		match extension.as_str() {
+			"csv" => self.add_csv(&text, ','),
+			"tsv" => self.add_csv(&text, '\t'),
+			"json" => match serde_json::from_str(&text) {
+				Ok(json) => self.add_json(&json),
+				Err(e) => anyhow::bail!("Error parsing {}: {}", filename.display(), e),
+			},
+			"po" | "pot" => self.add_po(&text),
+			"yaml" | "yml" => match YamlLoader::load_from_str(&text) {
+				Ok(docs) => {
+					for doc in &docs {
+						self.add_yaml(doc);
+					}
+				},
+				Err(e) => anyhow::bail!("Error parsing {}: {}", filename.display(), e),
+			},
+			_ => self.add_text(&text),
+		}
+		Ok(())
+	}
+
+	pub fn codepoints(&self) -> Vec<u32> {
+		self.codepoints.iter().copied().collect()
+	}
+
+	pub fn len(&self) -> usize {
+		self.codepoints.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.codepoints.is_empty()
+	}
+
+	// every cell, with quoted cells, and "" for quotes
+	fn add_csv(&mut self, text: &str, separator: char) {
+		let mut cell = String::new();
+		let mut in_quotes = false;
+		let mut chars = text.chars().peekable();
+		while let Some(c) = chars.next() {
+			match c {
+				'"' if in_quotes && chars.peek() == Some(&'"') => {
+					chars.next();
+					cell.push('"');
+				},
+				'"' => in_quotes = !in_quotes,
+				c if !in_quotes && (c == separator || c == '\n' || c == '\r') => {
+					self.add_text(&cell);
+					cell.clear();
+				},
+				c => cell.push(c),
+			}
+		}
+		self.add_text(&cell);
+	}
+
+	fn add_json(&mut self, json: &serde_json::Value) {
+		match json {
+			serde_json::Value::String(s) => self.add_text(s),
+			serde_json::Value::Array(a) => {
+				for v in a {
+					self.add_json(v);
+				}
+			},
+			serde_json::Value::Object(o) => {
+				for v in o.values() {
+					self.add_json(v);
+				}
+			},
+			serde_json::Value::Number(n) => self.add_text(&n.to_string()),
+			_ => {},
+		}
+	}
+
+	fn add_yaml(&mut self, yaml: &Yaml) {
+		match yaml {
+			Yaml::String(s) | Yaml::Real(s) => self.add_text(s),
+			Yaml::Integer(i) => self.add_text(&i.to_string()),
+			Yaml::Array(a) => {
+				for v in a {
+					self.add_yaml(v);
+				}
+			},
+			Yaml::Hash(h) => {
+				for v in h.values() {
+					self.add_yaml(v);
+				}
+			},
+			_ => {},
+		}
+	}
+
+	// translations, or the msgid for untranslated entries, the header is skipped
+	fn add_po(&mut self, text: &str) {
+		let mut ids: Vec<String> = Vec::new();
+		let mut strs: Vec<String> = Vec::new();
+		// continuation lines belong to a msgstr for Some(true), to a msgid for Some(false)
+		let mut in_str = None;
+
+		for line in text.lines().chain(std::iter::once("")) {
+			let line = line.trim();
+			if line.is_empty() {
+				let is_header = ids.first().map(|i| i.is_empty()).unwrap_or(false);
+				if !is_header {
+					if strs.iter().any(|s| !s.is_empty()) {
+						strs.iter().for_each(|s| self.add_text(s));
+					} else {
+						ids.iter().for_each(|s| self.add_text(s));
+					}
+				}
+				ids.clear();
+				strs.clear();
+				in_str = None;
+			} else if line.starts_with('#') {
+				in_str = None;
+			} else if line.starts_with('"') {
+				let s = CodepointCollector::po_unquote(line);
+				match in_str {
+					Some(true) => strs.last_mut().map(|l| l.push_str(&s)),
+					Some(false) => ids.last_mut().map(|l| l.push_str(&s)),
+					None => None,
+				};
+			} else if let Some((keyword, rest)) = line.split_once(char::is_whitespace) {
+				let s = CodepointCollector::po_unquote(rest.trim());
+				if keyword.starts_with("msgstr") {
+					strs.push(s);
+					in_str = Some(true);
+				} else if keyword.starts_with("msgid") {
+					ids.push(s);
+					in_str = Some(false);
+				} else {
+					// e.g. msgctxt
+					in_str = None;
+				}
+			}
+		}
+	}
+
+	fn po_unquote(s: &str) -> String {
+		let s = s.strip_prefix('"').unwrap_or(s);
+		let s = s.strip_suffix('"').unwrap_or(s);
+		let mut out = String::new();
+		let mut chars = s.chars();
+		while let Some(c) = chars.next() {
+			if c == '\\' {
+				match chars.next() {
+					Some('n') => out.push('\n'),
+					Some('t') => out.push('\t'),
+					Some(c) => out.push(c),
+					None => {},
+				}
+			} else {
+				out.push(c);
+			}
+		}
+		out
+	}
+}
@@ -1,16 +1,556 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
 
-use ab_glyph::{point, Font as AbFont, FontRef, PxScale, ScaleFont};
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ab_glyph::{point, Font as AbFont, FontRef, PxScale, ScaleFont, VariableFont};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use image::{DynamicImage, GenericImage, GenericImageView, ImageFormat};
 use om_fork_distance_field::DistanceFieldExt;
+use serde::Serialize;
 
 use crate::atlas::AtlasFitter;
+use crate::font::BitmapFont;
+use crate::font::FontEffects;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderMode {
+	#[default]
+	#[serde(rename = "sdf")]
+	DistanceField, // signed distance field, grey in all channels
+	Bitmap,       // 1-bit, thresholded coverage in alpha
+	Coverage,     // antialiased coverage in alpha
+	Supersampled, // supersampled, antialiased coverage in alpha
+}
+
+impl FromStr for RenderMode {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"sdf" | "distancefield" => Ok(RenderMode::DistanceField),
+			"bitmap" => Ok(RenderMode::Bitmap),
+			"coverage" => Ok(RenderMode::Coverage),
+			"supersampled" => Ok(RenderMode::Supersampled),
+			m => Err(format!(
+				"Unknown render mode {:?}, use one of sdf, bitmap, coverage, supersampled",
+				m
+			)),
+		}
+	}
+}
+
+impl RenderMode {
+	fn from_u8(v: u8) -> anyhow::Result<RenderMode> {
+		match v {
+			0 => Ok(RenderMode::DistanceField),
+			1 => Ok(RenderMode::Bitmap),
+			2 => Ok(RenderMode::Coverage),
+			3 => Ok(RenderMode::Supersampled),
+			v => anyhow::bail!("Unknown render mode {}", v),
+		}
+	}
+
+	fn as_u8(&self) -> u8 {
+		match self {
+			RenderMode::DistanceField => 0,
+			RenderMode::Bitmap => 1,
+			RenderMode::Coverage => 2,
+			RenderMode::Supersampled => 3,
+		}
+	}
+}
+
+impl std::fmt::Display for RenderMode {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let name = match self {
+			RenderMode::DistanceField => "sdf",
+			RenderMode::Bitmap => "bitmap",
+			RenderMode::Coverage => "coverage",
+			RenderMode::Supersampled => "supersampled",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+// what the font size measures, the scale of each input font is derived from it
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeMode {
+	#[default]
+	Scale, // ascent to descent, as used by the rasterizer, kept for compatibility
+	Em,         // em size in pixels, like css font-size
+	CapHeight,  // height of capital letters, measured on H, in pixels
+	LineHeight, // ascent to descent, plus line gap, in pixels
+}
+
+impl SizeMode {
+	fn from_u8(v: u8) -> anyhow::Result<SizeMode> {
+		match v {
+			0 => Ok(SizeMode::Scale),
+			1 => Ok(SizeMode::Em),
+			2 => Ok(SizeMode::CapHeight),
+			3 => Ok(SizeMode::LineHeight),
+			v => anyhow::bail!("Unknown size mode {}", v),
+		}
+	}
+
+	fn as_u8(&self) -> u8 {
+		match self {
+			SizeMode::Scale => 0,
+			SizeMode::Em => 1,
+			SizeMode::CapHeight => 2,
+			SizeMode::LineHeight => 3,
+		}
+	}
+}
+
+impl FromStr for SizeMode {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"scale" => Ok(SizeMode::Scale),
+			"em" => Ok(SizeMode::Em),
+			"cap-height" => Ok(SizeMode::CapHeight),
+			"line-height" => Ok(SizeMode::LineHeight),
+			m => Err(format!(
+				"Unknown size mode {:?}, use one of scale, em, cap-height, line-height",
+				m
+			)),
+		}
+	}
+}
+
+impl std::fmt::Display for SizeMode {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let name = match self {
+			SizeMode::Scale => "scale",
+			SizeMode::Em => "em",
+			SizeMode::CapHeight => "cap-height",
+			SizeMode::LineHeight => "line-height",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct FontOptions {
+	texsize: u32,
+	maximum_texsize: Option<u32>,
+	size: u32,
+	size_mode: SizeMode,
+	border: u32,
+	distancefield_scale: u16,
+	distancefield_max_distance: u16,
+	render_mode: RenderMode,
+	effects: FontEffects,
+	threshold: u8,
+	supersample: u16,
+	glyphs_only: bool,
+	kerning: bool,
+	codepoints: Option<Vec<u32>>,
+	face_index: u32,
+	variations: Vec<([u8; 4], f32)>,
+}
+
+impl Default for FontOptions {
+	fn default() -> Self {
+		Self {
+			texsize: 1024,
+			maximum_texsize: None,
+			size: 16,
+			size_mode: SizeMode::default(),
+			border: 0,
+			distancefield_scale: 4,
+			distancefield_max_distance: 2,
+			render_mode: RenderMode::default(),
+			effects: FontEffects::default(),
+			threshold: 128,
+			supersample: 4,
+			glyphs_only: false,
+			kerning: false,
+			codepoints: None,
+			face_index: 0,
+			variations: Vec::new(),
+		}
+	}
+}
+
+impl FontOptions {
+	pub fn with_texsize(mut self, texsize: u32) -> Self {
+		self.texsize = texsize;
+		self
+	}
+	pub fn with_maximum_texsize(mut self, maximum_texsize: u32) -> Self {
+		self.maximum_texsize = Some(maximum_texsize);
+		self
+	}
+	pub fn with_size(mut self, size: u32) -> Self {
+		self.size = size;
+		self
+	}
+	pub fn with_size_mode(mut self, size_mode: SizeMode) -> Self {
+		self.size_mode = size_mode;
+		self
+	}
+	pub fn with_border(mut self, border: u32) -> Self {
+		self.border = border;
+		self
+	}
+	pub fn with_distancefield_scale(mut self, distancefield_scale: u16) -> Self {
+		self.distancefield_scale = distancefield_scale;
+		self
+	}
+	pub fn with_distancefield_max_distance(mut self, distancefield_max_distance: u16) -> Self {
+		self.distancefield_max_distance = distancefield_max_distance;
+		self
+	}
+	pub fn with_render_mode(mut self, render_mode: RenderMode) -> Self {
+		self.render_mode = render_mode;
+		self
+	}
+	pub fn with_threshold(mut self, threshold: u8) -> Self {
+		self.threshold = threshold;
+		self
+	}
+	pub fn with_effects(mut self, effects: FontEffects) -> Self {
+		self.effects = effects;
+		self
+	}
+	pub fn with_supersample(mut self, supersample: u16) -> Self {
+		self.supersample = supersample;
+		self
+	}
+	pub fn with_glyphs_only(mut self, glyphs_only: bool) -> Self {
+		self.glyphs_only = glyphs_only;
+		self
+	}
+	pub fn with_kerning(mut self, kerning: bool) -> Self {
+		self.kerning = kerning;
+		self
+	}
+	// only create glyphs for these, instead of everything up to 127
+	pub fn with_codepoints(mut self, codepoints: Vec<u32>) -> Self {
+		self.codepoints = Some(codepoints);
+		self
+	}
+	// face to use from .ttc, or .otc collections
+	pub fn with_face_index(mut self, face_index: u32) -> Self {
+		self.face_index = face_index;
+		self
+	}
+	// variable font axis values, e.g. from parse_variations
+	pub fn with_variations(mut self, variations: Vec<([u8; 4], f32)>) -> Self {
+		self.variations = variations;
+		self
+	}
+
+	// wght=700,wdth=90
+	pub fn parse_variations(s: &str) -> Result<Vec<([u8; 4], f32)>, String> {
+		let mut variations = Vec::new();
+		for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+			let (tag, value) = match part.split_once('=') {
+				Some((tag, value)) => (tag.trim(), value.trim()),
+				None => return Err(format!("Invalid variation {:?}, use axis=value", part)),
+			};
+			if tag.is_empty() || tag.len() > 4 || !tag.is_ascii() {
+				return Err(format!(
+					"Invalid axis {:?}, use a tag with up to 4 characters, e.g. wght",
+					tag
+				));
+			}
+			let value = match value.parse::<f32>() {
+				Ok(value) => value,
+				Err(_) => return Err(format!("Invalid value {:?} for axis {}", value, tag)),
+			};
+			// tags are padded with spaces
+			let mut t = [b' '; 4];
+			t[..tag.len()].copy_from_slice(tag.as_bytes());
+			variations.push((t, value));
+		}
+		Ok(variations)
+	}
+}
 
-#[derive(Debug, Copy, Clone)]
+enum GlyphSource<'a> {
+	Outline(Box<FontRef<'a>>),
+	Bitmap(BitmapFont),
+}
+
+impl<'a> GlyphSource<'a> {
+	// face_index is only used for collections
+	fn load(filename: &str, buffer: &'a [u8], face_index: u32) -> anyhow::Result<GlyphSource<'a>> {
+		if BitmapFont::is_bitmap_font(filename) {
+			return Ok(GlyphSource::Bitmap(BitmapFont::load(filename)?));
+		}
+		let index = match GlyphSource::faces_in_collection(buffer) {
+			Some(faces) if face_index >= faces => anyhow::bail!(
+				"Face index {} out of range, {} has {} faces",
+				face_index,
+				filename,
+				faces
+			),
+			Some(faces) => {
+				println!("Using face {} of {} in {}", face_index, faces, filename);
+				face_index
+			},
+			None => {
+				if face_index != 0 {
+					println!(
+						"Ignoring face index for {}, it is not a collection",
+						filename
+					);
+				}
+				0
+			},
+		};
+		match FontRef::try_from_slice_and_index(buffer, index) {
+			Ok(font) => Ok(GlyphSource::Outline(Box::new(font))),
+			Err(e) => anyhow::bail!("Error reading font {}: {}", filename, e),
+		}
+	}
+
+	// number of faces for .ttc, and .otc, None for single fonts
+	fn faces_in_collection(buffer: &[u8]) -> Option<u32> {
+		if buffer.len() < 12 || &buffer[0..4] != b"ttcf" {
+			return None;
+		}
+		(&buffer[8..12]).read_u32::<BigEndian>().ok()
+	}
+
+	// false if the font has no such axis
+	fn set_variation(&mut self, tag: &[u8; 4], value: f32) -> bool {
+		match self {
+			GlyphSource::Outline(font) => font.set_variation(tag, value),
+			GlyphSource::Bitmap(_) => false,
+		}
+	}
+
+	// e.g. "wght 100..900, wdth 75..100"
+	fn axes(&self) -> String {
+		match self {
+			GlyphSource::Outline(font) => font
+				.variations()
+				.iter()
+				.map(|a| {
+					format!(
+						"{} {}..{}",
+						String::from_utf8_lossy(&a.tag).trim_end(),
+						a.min_value,
+						a.max_value
+					)
+				})
+				.collect::<Vec<_>>()
+				.join(", "),
+			GlyphSource::Bitmap(_) => String::new(),
+		}
+	}
+
+	fn has_glyph(&self, ch: char) -> bool {
+		match self {
+			GlyphSource::Outline(font) => font.glyph_id(ch).0 != 0,
+			GlyphSource::Bitmap(font) => font.glyph(ch as u32).is_some(),
+		}
+	}
+
+	// index of the first source with a glyph for ch, or of the first source
+	fn find(sources: &[GlyphSource<'a>], ch: char) -> usize {
+		sources.iter().position(|s| s.has_glyph(ch)).unwrap_or(0)
+	}
+
+	// the scale for the rasterizer, which measures from ascent to descent
+	fn px_scale(&self, size_mode: SizeMode, size: u32) -> anyhow::Result<f32> {
+		let size = size as f32;
+		match self {
+			GlyphSource::Outline(font) => {
+				let height = font.height_unscaled();
+				let reference = match size_mode {
+					SizeMode::Scale => height,
+					SizeMode::Em => match font.units_per_em() {
+						Some(units_per_em) => units_per_em,
+						None => anyhow::bail!("Font has no valid units per em"),
+					},
+					// H sits on the baseline, min, and max of the bounds are flipped in font units
+					SizeMode::CapHeight => match font.outline(font.glyph_id('H')) {
+						Some(o) => (o.bounds.max.y - o.bounds.min.y).abs(),
+						None => anyhow::bail!("Font has no H to measure the cap height"),
+					},
+					SizeMode::LineHeight => height + font.line_gap_unscaled(),
+				};
+				Ok(size * height / reference)
+			},
+			GlyphSource::Bitmap(font) => Ok(font.pixel_size as f32),
+		}
+	}
+
+	// em size, and line height in pixels for the given scale
+	fn metrics(&self, scale: f32) -> (f32, f32) {
+		match self {
+			GlyphSource::Outline(font) => {
+				let height = font.height_unscaled();
+				let units_per_em = font.units_per_em().unwrap_or(height);
+				(
+					scale * units_per_em / height,
+					scale * (height + font.line_gap_unscaled()) / height,
+				)
+			},
+			GlyphSource::Bitmap(font) => (font.pixel_size as f32, font.pixel_size as f32),
+		}
+	}
+
+	fn advance(&self, ch: char, scale: f32) -> f32 {
+		match self {
+			GlyphSource::Outline(font) => {
+				let scale = PxScale::from(scale);
+				let glyph_id = font.glyph_id(ch);
+				let next_glyph_id = font.glyph_id('#'); // Using '#' to match original two-character approach
+
+				// Get scaled font for metrics
+				let scaled_font = font.as_scaled(scale);
+				let advance = scaled_font.h_advance(glyph_id);
+				let kern = scaled_font.kern(glyph_id, next_glyph_id);
+				advance + kern
+			},
+			GlyphSource::Bitmap(font) => match font.glyph(ch as u32) {
+				Some(g) => g.advance as f32,
+				None => 0.0,
+			},
+		}
+	}
+
+	// relative to the advance, which already includes the kerning against '#'
+	fn kerning(&self, first: char, second: char, scale: f32) -> f32 {
+		match self {
+			GlyphSource::Outline(font) => {
+				let scaled_font = font.as_scaled(PxScale::from(scale));
+				let first = font.glyph_id(first);
+				scaled_font.kern(first, font.glyph_id(second))
+					- scaled_font.kern(first, font.glyph_id('#'))
+			},
+			GlyphSource::Bitmap(_) => 0.0,
+		}
+	}
+
+	// width, height, and bottom (relative to baseline, down is positive) in pixels
+	fn bounds(&self, ch: char, scale: f32) -> Option<(u32, u32, f32)> {
+		match self {
+			GlyphSource::Outline(font) => {
+				let scale = PxScale::from(scale);
+				let start = point(0.0, 0.0 /*+ v_metrics.ascent*/);
+				let glyph = font.glyph_id(ch).with_scale_and_position(scale, start);
+				font.outline_glyph(glyph).map(|outlined| {
+					let bounds = outlined.px_bounds();
+					let bb_width = (bounds.max.x - bounds.min.x) as u32;
+					let bb_height = (bounds.max.y - bounds.min.y) as u32;
+					(bb_width, bb_height, bounds.max.y)
+				})
+			},
+			GlyphSource::Bitmap(font) => match font.glyph(ch as u32) {
+				Some(g) if g.width > 0 && g.height > 0 => {
+					Some((g.width, g.height, -g.y_offset as f32))
+				},
+				_ => None,
+			},
+		}
+	}
+
+	// coverage as grey in all channels, scaled up by scale_factor, including border
+	fn rasterize(
+		&self,
+		ch: char,
+		scale: f32,
+		scale_factor: u32,
+		border: u32,
+	) -> Option<DynamicImage> {
+		match self {
+			GlyphSource::Outline(font) => {
+				let scale = PxScale::from(scale * scale_factor as f32);
+				let start = point(0.0, 0.0 /*+ v_metrics.ascent*/);
+				let glyph = font.glyph_id(ch).with_scale_and_position(scale, start);
+
+				font.outline_glyph(glyph).map(|outlined| {
+					let bounds = outlined.px_bounds();
+					let bb_width = (bounds.max.x - bounds.min.x) as u32;
+					let bb_height = (bounds.max.y - bounds.min.y) as u32;
+					let w = bb_width + 2 * border * scale_factor;
+					let h = bb_height + 2 * border * scale_factor;
+					let mut glyph_image = image::DynamicImage::new_rgba8(w, h);
+
+					outlined.draw(|x, y, v| {
+						let v = (v * 255.0) as u8;
+						let pixel = image::Rgba([v, v, v, v]);
+						glyph_image.put_pixel(
+							x + border * scale_factor,
+							y + border * scale_factor,
+							pixel,
+						);
+					});
+					glyph_image
+				})
+			},
+			GlyphSource::Bitmap(font) => match font.glyph(ch as u32) {
+				Some(g) if g.width > 0 && g.height > 0 => {
+					let w = (g.width + 2 * border) * scale_factor;
+					let h = (g.height + 2 * border) * scale_factor;
+					let mut glyph_image = image::DynamicImage::new_rgba8(w, h);
+					for y in 0..g.height * scale_factor {
+						for x in 0..g.width * scale_factor {
+							// nearest neighbour, pixel fonts stay crisp
+							let v = g.get(x / scale_factor, y / scale_factor);
+							let pixel = image::Rgba([v, v, v, v]);
+							glyph_image.put_pixel(
+								x + border * scale_factor,
+								y + border * scale_factor,
+								pixel,
+							);
+						}
+					}
+					Some(glyph_image)
+				},
+				_ => None,
+			},
+		}
+	}
+}
+
+// white, with the coverage mapped into alpha
+fn alpha_image(coverage: &DynamicImage, f: impl Fn(u8) -> u8) -> DynamicImage {
+	let (w, h) = coverage.dimensions();
+	let mut img = image::DynamicImage::new_rgba8(w, h);
+	for y in 0..h {
+		for x in 0..w {
+			let a = f(coverage.get_pixel(x, y)[0]);
+			img.put_pixel(x, y, image::Rgba([255, 255, 255, a]));
+		}
+	}
+	img
+}
+
+// box filter the supersampled coverage down to the final glyph size
+fn downsample(coverage: &DynamicImage, w: u32, h: u32, factor: u32) -> DynamicImage {
+	let (cw, ch) = coverage.dimensions();
+	let mut img = image::DynamicImage::new_rgba8(w, h);
+	for y in 0..h {
+		for x in 0..w {
+			let mut sum = 0u32;
+			for sy in y * factor..((y + 1) * factor).min(ch) {
+				for sx in x * factor..((x + 1) * factor).min(cw) {
+					sum += coverage.get_pixel(sx, sy)[0] as u32;
+				}
+			}
+			let a = (sum / (factor * factor)) as u8;
+			img.put_pixel(x, y, image::Rgba([255, 255, 255, a]));
+		}
+	}
+	img
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
 pub struct Glyph {
-	pub codepoint: u8,
+	pub codepoint: u32,
 	pub width:     u32,
 	pub height:    u32,
 	pub x:         u32,
@@ -18,6 +558,7 @@ pub struct Glyph {
 	pub advance:   u16,
 	pub y_offset:  f32,
 	pub matrix:    [f32; 6],
+	pub page:      u16,
 }
 /*
 impl std::fmt::Debug for Glyph {
@@ -31,7 +572,7 @@ impl std::fmt::Debug for Glyph {
 }
 */
 impl Glyph {
-	pub fn new(codepoint: u8, width: u32, height: u32) -> Glyph {
+	pub fn new(codepoint: u32, width: u32, height: u32) -> Glyph {
 		Glyph {
 			codepoint: codepoint,
 			width:     width,
@@ -41,6 +582,7 @@ impl Glyph {
 			advance:   0,
 			y_offset:  0.0,
 			matrix:    [0.0; 6],
+			page:      0,
 		}
 	}
 	fn recalc_matrix(&mut self, texsize: u32) {
@@ -50,20 +592,46 @@ impl Glyph {
 		let scale_y = self.height as f32 / texsize as f32;
 		self.matrix = [scale_x, 0.0, sx, 0.0, scale_y, sy];
 	}
+	// the matrix is 2 rows of scale x, shear, offset, see recalc_matrix
+	// rounded, normalized coordinates of non power of two textures are not exact
 	fn recalc_from_matrix(&mut self, texsize: u32) {
-		self.width = (self.matrix[0 * 3 + 0] * texsize as f32).trunc() as u32;
-		self.height = (self.matrix[1 * 3 + 1] * texsize as f32).trunc() as u32;
-		self.x = (self.matrix[0 * 3 + 2] * texsize as f32).trunc() as u32;
-		self.y = (self.matrix[1 * 3 + 2] * texsize as f32).trunc() as u32;
+		let texels = |v: f32| (v * texsize as f32).round() as u32;
+		let [scale_x, _, sx, _, scale_y, sy] = self.matrix;
+		self.width = texels(scale_x);
+		self.height = texels(scale_y);
+		self.x = texels(sx);
+		self.y = texels(sy);
 	}
 }
 
+#[derive(Debug, Serialize)]
+pub struct FontInfo {
+	pub format:        String,
+	pub version:       u32,
+	pub size:          u32,
+	pub size_mode:     SizeMode,
+	pub render_mode:   RenderMode,
+	pub em_size:       f32,
+	pub line_height:   f32,
+	pub texsize:       Option<u32>,
+	pub pages:         u32,
+	pub effects:       Option<FontEffects>,
+	pub kerning_pairs: usize,
+	pub glyphs:        Vec<Glyph>,
+}
+
 pub struct Font {
-	pub glyphs: Vec<Glyph>,
-	texsize:    u32,
-	size:       u32,
-	border:     u32,
-	pub image:  DynamicImage,
+	pub glyphs:  Vec<Glyph>,
+	texsize:     u32,
+	size:        u32,
+	size_mode:   SizeMode,
+	render_mode: RenderMode,
+	em_size:     f32,
+	line_height: f32,
+	border:      u32,
+	pub pages:   Vec<DynamicImage>,
+	effects:     Option<(FontEffects, f32)>,
+	kerning:     HashMap<(u32, u32), f32>,
 }
 
 impl std::fmt::Debug for Font {
@@ -82,14 +650,80 @@ impl Font {
 
 	fn new(texsize: u32, size: u32, border: u32) -> Font {
 		Font {
-			glyphs:  Vec::new(),
-			texsize: texsize,
-			size:    size,
-			border:  border,
-			image:   image::DynamicImage::new_rgba8(texsize, texsize),
+			glyphs: Vec::new(),
+			texsize,
+			size,
+			size_mode: SizeMode::default(),
+			render_mode: RenderMode::default(),
+			em_size: size as f32,
+			line_height: size as f32,
+			border,
+			pages: Vec::new(),
+			effects: None,
+			kerning: HashMap::new(),
+		}
+	}
+
+	pub fn effects(&self) -> Option<&FontEffects> {
+		self.effects.as_ref().map(|(effects, _)| effects)
+	}
+
+	// range of the fill distance field in texels, if known
+	pub fn distance_range(&self) -> Option<f32> {
+		self.effects.as_ref().map(|(_, fill_range)| *fill_range)
+	}
+
+	pub fn size(&self) -> u32 {
+		self.size
+	}
+
+	pub fn size_mode(&self) -> SizeMode {
+		self.size_mode
+	}
+
+	// how the texture was rendered, distance fields for fonts before version 7
+	pub fn render_mode(&self) -> RenderMode {
+		self.render_mode
+	}
+
+	// in pixels, the size for older fonts
+	pub fn em_size(&self) -> f32 {
+		self.em_size
+	}
+
+	// in pixels, the size for older fonts
+	pub fn line_height(&self) -> f32 {
+		self.line_height
+	}
+
+	pub fn texsize(&self) -> u32 {
+		self.texsize
+	}
+
+	// the first page, the only one of single page fonts, None for fonts loaded without texture
+	pub fn image(&self) -> Option<&DynamicImage> {
+		self.pages.first()
+	}
+
+	pub fn glyph(&self, codepoint: u32) -> Option<&Glyph> {
+		// glyphs are stored sorted by codepoint, and often indexed by it
+		match self.glyphs.get(codepoint as usize) {
+			Some(g) if g.codepoint == codepoint => Some(g),
+			_ => match self
+				.glyphs
+				.binary_search_by_key(&codepoint, |g| g.codepoint)
+			{
+				Ok(idx) => self.glyphs.get(idx),
+				Err(_) => self.glyphs.iter().find(|g| g.codepoint == codepoint),
+			},
 		}
 	}
 
+	// in pixels, to be added to the advance of the first glyph, which doesn't include it
+	pub fn kerning(&self, first: u32, second: u32) -> f32 {
+		self.kerning.get(&(first, second)).copied().unwrap_or(0.0)
+	}
+
 	fn recalc_matrix(&mut self, texsize: u32) {
 		for g in &mut self.glyphs {
 			g.recalc_matrix(texsize);
@@ -105,136 +739,187 @@ impl Font {
 	fn load_omfont_v2(&mut self, filename: &str) -> anyhow::Result<u32> {
 		let f = match File::open(filename) {
 			Ok(f) => f,
-			Err(_) => anyhow::bail!("io"),
+			Err(e) => anyhow::bail!("Error opening {}: {}", filename, e),
 		};
 
 		let mut bufreader = BufReader::new(f);
 		let chunk_magic = [0x4fu8, 0x4d, 0x46, 0x4f, 0x4e, 0x54];
 		for m in &chunk_magic {
-			let b = bufreader.read_u8().unwrap_or(0);
+			let b = bufreader.read_u8()?;
 			if b != *m {
-				anyhow::bail!("Broken chunk magic");
+				anyhow::bail!("Broken chunk magic in {}, expected OMFONT", filename);
 			}
 		}
-		let version = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
-		if version != 2 {
-			anyhow::bail!("Unsupported version");
+		let version = bufreader.read_u32::<LittleEndian>()?;
+		if !(2..=7).contains(&version) {
+			anyhow::bail!("Unsupported version {} in {}", version, filename);
 		}
 
-		self.size = bufreader.read_u16::<LittleEndian>().unwrap_or(0) as u32;
-		let count = bufreader.read_u16::<LittleEndian>().unwrap_or(0);
+		self.size = bufreader.read_u16::<LittleEndian>()? as u32;
+		self.em_size = self.size as f32;
+		self.line_height = self.size as f32;
+		let count = bufreader.read_u16::<LittleEndian>()?;
+		let page_count = if version >= 3 {
+			bufreader.read_u16::<LittleEndian>()?
+		} else {
+			1
+		};
+		if version >= 4 {
+			let (effects, fill_range) = FontEffects::read(&mut bufreader)?;
+			// version 5 always has an effects block, even without effects
+			if !effects.is_empty() {
+				self.effects = Some((effects, fill_range));
+			}
+		}
+		if version >= 6 {
+			self.size_mode = SizeMode::from_u8(bufreader.read_u8()?)?;
+			let mut reserved = [0u8; 3];
+			bufreader.read_exact(&mut reserved)?;
+			self.em_size = bufreader.read_f32::<LittleEndian>()?;
+			self.line_height = bufreader.read_f32::<LittleEndian>()?;
+		}
+		if version >= 7 {
+			self.render_mode = RenderMode::from_u8(bufreader.read_u8()?)?;
+			let mut reserved = [0u8; 3];
+			bufreader.read_exact(&mut reserved)?;
+		}
 
 		let mut codepoints = Vec::new();
 
 		for _c in 0..count {
-			let codepoint = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
+			let codepoint = bufreader.read_u32::<LittleEndian>()?;
 			codepoints.push(codepoint);
 		}
 
 		for c in 0..count {
 			let codepoint = codepoints[c as usize];
-			let mut glyph = Glyph::new(codepoint as u8, 0, 0);
+			let mut glyph = Glyph::new(codepoint, 0, 0);
 			for m in &mut glyph.matrix {
-				*m = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0);
+				*m = bufreader.read_f32::<LittleEndian>()?;
+			}
+			glyph.advance = bufreader.read_f32::<LittleEndian>()? as u16;
+			glyph.y_offset = bufreader.read_f32::<LittleEndian>()?;
+			if version >= 3 {
+				glyph.page = bufreader.read_u16::<LittleEndian>()?;
 			}
-			glyph.advance = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0) as u16;
-			glyph.y_offset = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0);
 
 			self.glyphs.push(glyph);
 		}
 
-		Ok(0)
+		if version >= 5 {
+			let pair_count = bufreader.read_u32::<LittleEndian>()?;
+			for _p in 0..pair_count {
+				let first = bufreader.read_u32::<LittleEndian>()?;
+				let second = bufreader.read_u32::<LittleEndian>()?;
+				let kerning = bufreader.read_f32::<LittleEndian>()?;
+				self.kerning.insert((first, second), kerning);
+			}
+		}
+
+		Ok(page_count as u32)
 	}
 
+	// legacy OMFN, always 128 glyphs, texture coordinates are normalized already
 	fn load_omfont(&mut self, filename: &str) -> anyhow::Result<u32> {
 		let f = match File::open(filename) {
 			Ok(f) => f,
-			Err(_) => anyhow::bail!("io"),
+			Err(e) => anyhow::bail!("Error opening {}: {}", filename, e),
 		};
 
 		let mut bufreader = BufReader::new(f);
-		let magic = match bufreader.read_u32::<LittleEndian>() {
-			//.unwrap_or( 0xffff );
-			Ok(m) => m,
-			x => {
-				println!("{:?}", x);
-				anyhow::bail!("reading from buffer");
-			},
-		};
+		let magic = bufreader.read_u32::<LittleEndian>()?;
 		if magic != 0x4e464d4f {
 			println!("Got magic {:#08x} from {:?}", magic, bufreader);
-			anyhow::bail!("Broken file magic");
+			anyhow::bail!("Broken file magic in {}, expected OMFN", filename);
 		}
 
-		let _height = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
+		self.size = bufreader.read_u32::<LittleEndian>()?;
 
-		let mut tex_coords = [0f32; 4 * 2];
-		let mut v_pos = [0f32; 4 * 3];
+		// vertices in the order written by save_omfont
+		const UPPER_LEFT: usize = 0;
+		const LOWER_LEFT: usize = 1;
+		const LOWER_RIGHT: usize = 3;
+
+		// u, v
+		let mut tex_coords = [[0f32; 2]; 4];
+		// x, y, z
+		let mut v_pos = [[0f32; 3]; 4];
 
 		for codepoint in 0..128u8 {
 			for i in 0..4 {
-				tex_coords[i * 2 + 0] = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0);
-				tex_coords[i * 2 + 1] = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0);
-				v_pos[i * 2 + 0] = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0);
-				v_pos[i * 2 + 1] = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0);
-				v_pos[i * 2 + 2] = bufreader.read_f32::<LittleEndian>().unwrap_or(0.0);
+				for tc in tex_coords[i].iter_mut() {
+					*tc = bufreader.read_f32::<LittleEndian>()?;
+				}
+				for vp in v_pos[i].iter_mut() {
+					*vp = bufreader.read_f32::<LittleEndian>()?;
+				}
+			}
+			let [tex_l, tex_t] = tex_coords[UPPER_LEFT];
+			let [tex_r, tex_b] = tex_coords[LOWER_RIGHT];
+			// relative to the baseline, up is positive
+			let v_bottom = v_pos[LOWER_LEFT][1];
+
+			let mut g = Glyph::new(codepoint as u32, 0, 0);
+			g.matrix = [tex_r - tex_l, 0.0, tex_l, 0.0, tex_b - tex_t, tex_t];
+			if self.texsize > 0 {
+				g.y_offset = -v_bottom / self.texsize as f32;
 			}
-			//			println!("{:#?}", tex_coords );
-			let tex_l = tex_coords[0 * 2 + 0];
-			let tex_t = tex_coords[0 * 2 + 1];
-			let tex_r = tex_coords[3 * 2 + 0];
-			let tex_b = tex_coords[3 * 2 + 1];
-
-			let tex_w = (tex_r - tex_l) * self.texsize as f32;
-			let tex_h = (tex_b - tex_t) * self.texsize as f32;
-
-			//			println!("{:?} {:?}", tex_w, tex_h );
-			let mut g = Glyph::new(codepoint, tex_w as u32, tex_h as u32);
-			g.x = (tex_l * self.texsize as f32) as u32;
-			g.y = (tex_t * self.texsize as f32) as u32;
 			self.glyphs.push(g);
-			//			let left_tx = bufreader.read_f32::<LittleEndian>().unwrap_or( 0.0 );
 		}
 		for codepoint in 0..128u8 {
-			let advance = bufreader.read_u16::<LittleEndian>().unwrap_or(0xff);
-			self.glyphs[codepoint as usize].advance = advance as u16;
+			let advance = bufreader.read_u16::<LittleEndian>()?;
+			self.glyphs[codepoint as usize].advance = advance;
+		}
+		if self.texsize > 0 {
+			self.recalc_from_matrix(self.texsize);
+		}
+		Ok(1)
+	}
+
+	// 1 for legacy OMFN, the version for OMFONT
+	fn omfont_version(filename: &str) -> anyhow::Result<u32> {
+		let f = match File::open(filename) {
+			Ok(f) => f,
+			Err(e) => anyhow::bail!("Error opening {}: {}", filename, e),
+		};
+		let mut bufreader = BufReader::new(f);
+		let mut magic = [0u8; 6];
+		if bufreader.read_exact(&mut magic).is_err() {
+			anyhow::bail!("File {} is too short for a font", filename);
+		}
+		if &magic[0..4] == b"OMFN" {
+			Ok(1)
+		} else if &magic == b"OMFONT" {
+			Ok(bufreader.read_u32::<LittleEndian>()?)
+		} else {
+			anyhow::bail!(
+				"Unknown font format in {}, expected OMFONT, or legacy OMFN",
+				filename
+			)
 		}
-		Ok(0)
 	}
 
-	fn new_from_omfont(fontname: &str, texsize: u32) -> Font {
+	fn new_from_omfont(fontname: &str, texsize: u32) -> anyhow::Result<Font> {
 		let size = 40;
 		let border = 0;
 
-		let mut f = Font {
-			glyphs:  Vec::new(),
-			texsize: texsize,
-			size:    size,
-			border:  border,
-			image:   image::DynamicImage::new_rgba8(texsize, texsize),
-		};
-		match f.load_omfont_v2(fontname) {
-			Ok(_) => {
-				// calculate x, y, width, height
-				f.recalc_from_matrix(texsize);
-				//				println!("{:#?}", f );
-			},
-			Err(_) => {
-				match f.load_omfont(fontname) {
-					// for the moment we don't care
-					Ok(_) => {},
-					Err(_) => {},
-				};
-			},
-		};
+		let mut f = Font::new(texsize, size, border);
+		if Font::omfont_version(fontname)? == 1 {
+			f.load_omfont(fontname)?;
+		} else {
+			f.load_omfont_v2(fontname)?;
+			// calculate x, y, width, height
+			f.recalc_from_matrix(texsize);
+		}
 
-		f
+		Ok(f)
 	}
 	fn add_glyph(&mut self, glyph: Glyph) {
 		self.glyphs.push(glyph);
 	}
 
-	fn fit_glyphs(&mut self) -> bool {
+	// returns the number of pages needed, 0 if a glyph doesn't fit at all
+	fn fit_glyphs(&mut self) -> u32 {
 		let mut atlas_fitter = AtlasFitter::default().with_border(self.border);
 
 		for (idx, e) in self.glyphs.iter().enumerate() {
@@ -243,21 +928,18 @@ impl Font {
 
 		let pages = atlas_fitter.fit(self.texsize);
 
-		if pages.len() > 1 {
-			println!("Need {} pages to fit glyphs", pages.len());
-			return false;
-		}
-		for p in &pages {
+		for (page_index, p) in pages.iter().enumerate() {
 			for e in &p.entries {
-				let mut glyph = &mut self.glyphs[e.id];
+				let glyph = &mut self.glyphs[e.id];
 				glyph.x = e.x;
 				glyph.y = e.y;
+				glyph.page = page_index as u16;
 			}
 		}
-		true
+		pages.len() as u32
 	}
 
-	pub fn set_pixel(&mut self, x: i32, y: i32, v: f32) {
+	pub fn set_pixel(&mut self, page: usize, x: i32, y: i32, v: f32) {
 		if x < 0 || y < 0 {
 			return;
 		}
@@ -269,10 +951,13 @@ impl Font {
 		//		println!("set_pixel {:?}, {:?}, {:?}", x, y, v );
 		let v = (v * 255.0) as u8;
 		let pixel = image::Rgba([v, v, v, v]);
-		self.image.put_pixel(x, y, pixel);
+		if let Some(image) = self.pages.get_mut(page) {
+			image.put_pixel(x, y, pixel);
+		}
 	}
 
-	fn blit_image(&mut self, gx: u32, gy: u32, img: &image::DynamicImage) {
+	fn blit_image(&mut self, page: usize, gx: u32, gy: u32, img: &image::DynamicImage) {
+		let image = &mut self.pages[page];
 		let w = img.dimensions().0;
 		let h = img.dimensions().1;
 
@@ -282,127 +967,364 @@ impl Font {
 				let tx = gx + x;
 				let ty = gy + y;
 				//				println!("{}, {} + {}, {} = {}, {}", gx, gy, h, w, tx, ty );
-				self.image.put_pixel(tx, ty, p);
+				image.put_pixel(tx, ty, p);
 			}
 		}
 	}
 
 	fn blit_glyphs(
 		&mut self,
-		font: FontRef,
-		distancefield_scale: u16,
-		distancefield_max_distance: u16,
+		sources: &[GlyphSource],
+		scales: &[f32],
+		options: &FontOptions,
 	) -> bool {
-		let scale_factor = match distancefield_scale {
-			0 => 1,
-			1 => 1,
-			f => f as u32,
-		};
+		for (idx, glyph_image) in self.render_glyphs(sources, scales, options) {
+			let g = self.glyphs[idx];
+			self.blit_image(g.page as usize, g.x, g.y, &glyph_image);
+		}
 
-		let scale = PxScale::from((self.size * scale_factor) as f32);
-		let start = point(0.0, 0.0 /*+ v_metrics.ascent*/);
+		true
+	}
 
-		let glyphs = self.glyphs.clone(); // needed to avoid borrow problem below :(
-		for g in glyphs {
-			let ch = g.codepoint as char;
-			//			println!("Blitting {:?}", ch );
-			let glyph_id = font.glyph_id(ch);
-			let glyph = glyph_id.with_scale_and_position(scale, start);
-
-			if let Some(outlined) = font.outline_glyph(glyph) {
-				let bounds = outlined.px_bounds();
-				let bb_width = (bounds.max.x - bounds.min.x) as u32;
-				let bb_height = (bounds.max.y - bounds.min.y) as u32;
-				let w = bb_width + 2 * self.border * scale_factor;
-				let h = bb_height + 2 * self.border * scale_factor;
-				let mut glyph_image = image::DynamicImage::new_rgba8(w, h);
-
-				outlined.draw(|x, y, v| {
-					let v = (v * 255.0) as u8;
-					let pixel = image::Rgba([v, v, v, v]);
-					glyph_image.put_pixel(
-						x + self.border * scale_factor,
-						y + self.border * scale_factor,
-						pixel,
-					);
-				});
+	// returns the rendered image for every glyph with an outline, by glyph index
+	// scales are the rasterizer scales of the sources
+	fn render_glyphs(
+		&self,
+		sources: &[GlyphSource],
+		scales: &[f32],
+		options: &FontOptions,
+	) -> Vec<(usize, DynamicImage)> {
+		let distancefield_scale = options.distancefield_scale;
+		let scale_factor = match options.render_mode {
+			RenderMode::DistanceField => match distancefield_scale {
+				0 => 1,
+				1 => 1,
+				f => f as u32,
+			},
+			RenderMode::Supersampled => options.supersample.max(1) as u32,
+			RenderMode::Bitmap | RenderMode::Coverage => 1,
+		};
 
-				//
-				if distancefield_scale >= 1 {
-					// downscale
-					let downscale_factor = 1.0 / distancefield_scale as f32;
-					let w = (w as f32 * downscale_factor) as u32;
-					let h = (h as f32 * downscale_factor) as u32;
-					let max_distance = distancefield_max_distance as u32 * scale_factor as u32;
-
-					let distance_field =
-						glyph_image
-							.grayscale()
-							.distance_field(om_fork_distance_field::Options {
-								size: (w as usize, h as usize),
-								max_distance: max_distance as usize,
-								..Default::default()
-							});
-					glyph_image = image::DynamicImage::new_rgba8(w, h);
-					for y in 0..h {
-						for x in 0..w {
-							let pixel = distance_field.get_pixel(x, y);
-							let luma = pixel[0];
-							let r = luma;
-							let g = luma;
-							let b = luma;
-							let a = luma;
-
-							let rgba = image::Rgba([r, g, b, a]);
-							glyph_image.put_pixel(x, y, rgba);
+		let mut glyph_images = Vec::new();
+		for (idx, g) in self.glyphs.iter().enumerate() {
+			let Some(ch) = char::from_u32(g.codepoint) else {
+				continue;
+			};
+			//			println!("Blitting {:?}", ch );
+			let idx_source = GlyphSource::find(sources, ch);
+			if let Some(mut glyph_image) =
+				sources[idx_source].rasterize(ch, scales[idx_source], scale_factor, self.border)
+			{
+				match options.render_mode {
+					RenderMode::DistanceField => {
+						if distancefield_scale >= 1 {
+							let (w, h) = glyph_image.dimensions();
+							// downscale
+							let downscale_factor = 1.0 / distancefield_scale as f32;
+							let w = (w as f32 * downscale_factor) as u32;
+							let h = (h as f32 * downscale_factor) as u32;
+							// effects need a wider range than the fill itself
+							let fill_range = options.distancefield_max_distance as f32;
+							let range = options.effects.distance_range(fill_range);
+							let max_distance = (range * scale_factor as f32).ceil() as u32;
+
+							let distance_field = glyph_image.grayscale().distance_field(
+								om_fork_distance_field::Options {
+									size:         (w as usize, h as usize),
+									max_distance: max_distance as usize,
+								},
+							);
+							glyph_image = image::DynamicImage::new_rgba8(w, h);
+							if !options.effects.is_empty() {
+								// signed distance in texels, positive inside, bilinear filtered
+								let distance_at = |x: i32, y: i32| -> f32 {
+									if x < 0 || y < 0 || x >= w as i32 || y >= h as i32 {
+										-range
+									} else {
+										let v = distance_field.get_pixel(x as u32, y as u32)[0];
+										(v as f32 / 255.0 - 0.5) * 2.0 * range
+									}
+								};
+								let distance = |x: f32, y: f32| -> f32 {
+									let (x0, y0) = (x.floor(), y.floor());
+									let (fx, fy) = (x - x0, y - y0);
+									let (x0, y0) = (x0 as i32, y0 as i32);
+									let top = distance_at(x0, y0) * (1.0 - fx)
+										+ distance_at(x0 + 1, y0) * fx;
+									let bottom = distance_at(x0, y0 + 1) * (1.0 - fx)
+										+ distance_at(x0 + 1, y0 + 1) * fx;
+									top * (1.0 - fy) + bottom * fy
+								};
+								for y in 0..h {
+									for x in 0..w {
+										let rgba = options
+											.effects
+											.encode(distance, x as f32, y as f32, fill_range);
+										glyph_image.put_pixel(x, y, image::Rgba(rgba));
+									}
+								}
+							} else {
+								for y in 0..h {
+									for x in 0..w {
+										let pixel = distance_field.get_pixel(x, y);
+										let luma = pixel[0];
+										let r = luma;
+										let g = luma;
+										let b = luma;
+										let a = luma;
+
+										let rgba = image::Rgba([r, g, b, a]);
+										glyph_image.put_pixel(x, y, rgba);
+									}
+								}
+							}
 						}
-					}
+					},
+					RenderMode::Bitmap => {
+						let threshold = options.threshold;
+						glyph_image =
+							alpha_image(&glyph_image, |v| if v >= threshold { 255 } else { 0 });
+					},
+					RenderMode::Coverage => {
+						glyph_image = alpha_image(&glyph_image, |v| v);
+					},
+					RenderMode::Supersampled => {
+						glyph_image = downsample(&glyph_image, g.width, g.height, scale_factor);
+					},
 				}
-				self.blit_image(g.x, g.y, &glyph_image);
+				glyph_images.push((idx, glyph_image));
 			}
 		}
 
-		true
+		glyph_images
+	}
+
+	// multi page fonts are only possible with version 3, everything else is a single page
+	fn page_count_in_omfont(filename: &str) -> u32 {
+		let f = match File::open(filename) {
+			Ok(f) => f,
+			Err(_) => return 1,
+		};
+		let mut bufreader = BufReader::new(f);
+		let mut magic = [0u8; 6];
+		if bufreader.read_exact(&mut magic).is_err() || &magic != b"OMFONT" {
+			return 1;
+		}
+		let version = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
+		if version < 3 {
+			return 1;
+		}
+		let _size = bufreader.read_u16::<LittleEndian>().unwrap_or(0);
+		let _count = bufreader.read_u16::<LittleEndian>().unwrap_or(0);
+		bufreader.read_u16::<LittleEndian>().unwrap_or(1) as u32
 	}
 
 	pub fn load(name: &str) -> anyhow::Result<Font> {
-		let pngname = format!("{}.png", name);
-		let img = image::open(&pngname).unwrap();
-		if img.dimensions().0 != img.dimensions().1 {
-			println!(
-				"Error: Non-square texture for font found with dimensions {:?}",
-				img.dimensions()
+		let fontname = format!("{}.omfont", name);
+
+		// single page fonts use name.png, multi page fonts name-0.png, name-1.png, ...
+		let page_count = Font::page_count_in_omfont(&fontname);
+		if page_count == 0 {
+			anyhow::bail!(
+				"Font {} has no texture, combine its glyphs into an atlas with omt-atlas first",
+				name
 			);
-			anyhow::bail!("Error: Non-square texture for font");
+		}
+		let pngnames = Font::texture_names(name, page_count);
+
+		let mut pages: Vec<DynamicImage> = Vec::new();
+		for pngname in &pngnames {
+			let img = match image::open(pngname) {
+				Ok(img) => img,
+				Err(e) => anyhow::bail!("Error opening texture {}: {}", pngname, e),
+			};
+			if img.dimensions().0 != img.dimensions().1 {
+				println!(
+					"Error: Non-square texture for font found with dimensions {:?}",
+					img.dimensions()
+				);
+				anyhow::bail!("Error: Non-square texture for font");
+			}
+			if let Some(first) = pages.first() {
+				if img.dimensions() != first.dimensions() {
+					anyhow::bail!("Error: All pages of a font need the same size");
+				}
+			}
+			pages.push(img);
 		}
 
-		let texsize = img.dimensions().0;
+		let texsize = pages[0].dimensions().0;
 
-		let fontname = format!("{}.omfont", name);
-		let mut font = Font::new_from_omfont(&fontname, texsize);
+		let mut font = Font::new_from_omfont(&fontname, texsize)?;
 
-		font.image = img;
+		font.pages = pages;
 
 		//		anyhow::bail!( "Font::load not implemented")
 		Ok(font)
 	}
-	fn save_omfont_v2(&self, filename: &str) -> anyhow::Result<u32> {
+	// loads the .omfont only, without any texture, and returns the page count, too
+	// for fonts without a texture the glyph sizes are in pixels
+	pub fn load_metrics(name: &str) -> anyhow::Result<(Font, u32)> {
+		let fontname = format!("{}.omfont", name);
+		let mut font = Font::new(1, 0, 0);
+		let page_count = font.load_omfont_v2(&fontname)?;
+		if page_count == 0 {
+			font.recalc_from_matrix(1);
+		}
+		Ok((font, page_count))
+	}
+
+	pub fn glyph_image_filename(name: &str, codepoint: u32) -> String {
+		let stem = Path::new(name)
+			.file_name()
+			.map(|n| n.to_string_lossy().to_string())
+			.unwrap_or_default();
+		format!("{}-glyphs/{}-{:04x}.png", name, stem, codepoint)
+	}
+
+	// rewrites the .omfont for glyphs placed into a shared atlas
+	pub fn save_in_atlas(
+		&mut self,
+		name: &str,
+		atlas_size: u32,
+		page_count: u32,
+	) -> anyhow::Result<()> {
+		self.texsize = atlas_size;
+		self.recalc_matrix(atlas_size);
+		for g in &mut self.glyphs {
+			g.y_offset /= atlas_size as f32;
+		}
+		let filename = format!("{}.omfont", name);
+		println!("Writing font data to {}", filename);
+		self.save_omfont_v2(&filename, page_count)?;
+		Ok(())
+	}
+
+	// texture names for the given page count, see load
+	fn texture_names(name: &str, page_count: u32) -> Vec<String> {
+		if page_count > 1 {
+			(0..page_count)
+				.map(|n| format!("{}-{}.png", name, n))
+				.collect()
+		} else {
+			vec![format!("{}.png", name)]
+		}
+	}
+
+	fn texsize_from_texture(name: &str, page_count: u32) -> Option<u32> {
+		let pngname = Font::texture_names(name, page_count).into_iter().next()?;
+		image::image_dimensions(pngname).ok().map(|(w, _h)| w)
+	}
+
+	// header, and glyph metrics, texsize is taken from the texture if not given
+	pub fn info(name: &str, texsize: Option<u32>) -> anyhow::Result<FontInfo> {
+		let fontname = format!("{}.omfont", name);
+		let version = Font::omfont_version(&fontname)?;
+		let pages = Font::page_count_in_omfont(&fontname);
+		let texsize = texsize.or_else(|| Font::texsize_from_texture(name, pages));
+		let font = Font::new_from_omfont(&fontname, texsize.unwrap_or(0))?;
+
+		Ok(FontInfo {
+			format: if version == 1 { "OMFN" } else { "OMFONT" }.to_string(),
+			version,
+			size: font.size,
+			size_mode: font.size_mode,
+			render_mode: font.render_mode,
+			em_size: font.em_size,
+			line_height: font.line_height,
+			texsize,
+			pages,
+			effects: font.effects().cloned(),
+			kerning_pairs: font.kerning.len(),
+			glyphs: font.glyphs,
+		})
+	}
+
+	// rewrites any font, e.g. legacy OMFN, with the current writer, and copies the textures
+	// returns the version written
+	pub fn convert(input: &str, output: &str, texsize: Option<u32>) -> anyhow::Result<u32> {
+		let fontname = format!("{}.omfont", input);
+		let version = Font::omfont_version(&fontname)?;
+		let page_count = Font::page_count_in_omfont(&fontname);
+		let texsize = match texsize.or_else(|| Font::texsize_from_texture(input, page_count)) {
+			Some(texsize) => texsize,
+			None if version == 1 => anyhow::bail!(
+				"Need the texture {}.png, or a texsize to convert legacy fonts",
+				input
+			),
+			None => 0,
+		};
+		let font = Font::new_from_omfont(&fontname, texsize)?;
+
+		let filename = format!("{}.omfont", output);
+		println!("Writing font data to {}", filename);
+		let new_version = font.save_omfont_v2(&filename, page_count)?;
+
+		if input != output {
+			let from = Font::texture_names(input, page_count);
+			let to = Font::texture_names(output, page_count);
+			for (from, to) in from.iter().zip(to.iter()) {
+				if Path::new(from).exists() {
+					println!("Copying texture {} to {}", from, to);
+					std::fs::copy(from, to)?;
+				}
+			}
+		}
+
+		Ok(new_version)
+	}
+
+	// page_count 0 marks fonts without a texture, with glyph metrics in pixels
+	fn save_omfont_v2(&self, filename: &str, page_count: u32) -> anyhow::Result<u32> {
 		let mut f = match File::create(filename) {
 			Ok(f) => f,
-			Err(_) => anyhow::bail!("io"),
+			Err(e) => anyhow::bail!("Error creating {}: {}", filename, e),
+		};
+		// only use newer versions when needed, stay compatible otherwise
+		let version: u32 = if self.render_mode != RenderMode::DistanceField {
+			7
+		} else if self.size_mode != SizeMode::Scale {
+			6
+		} else if !self.kerning.is_empty() {
+			5
+		} else if self.effects.is_some() {
+			4
+		} else if page_count != 1 {
+			3
+		} else {
+			2
 		};
 		f.write_all(&[
 			0x4f, 0x4d, 0x46, 0x4f, 0x4e, 0x54, // OMFONT
-			0x2, 0x00, 0x00, 0x00, // u32 version
 		])
 		.unwrap();
+		f.write_u32::<LittleEndian>(version).unwrap();
 
 		f.write_u16::<LittleEndian>(self.size as u16).unwrap();
 		f.write_u16::<LittleEndian>(self.glyphs.len() as u16)
 			.unwrap();
+		if version >= 3 {
+			f.write_u16::<LittleEndian>(page_count as u16).unwrap();
+		}
+		if version >= 4 {
+			match &self.effects {
+				Some((effects, fill_range)) => effects.write(&mut f, *fill_range)?,
+				None => FontEffects::default().write(&mut f, 0.0)?,
+			}
+		}
+		if version >= 6 {
+			f.write_u8(self.size_mode.as_u8())?;
+			f.write_all(&[0, 0, 0])?; // reserved
+			f.write_f32::<LittleEndian>(self.em_size)?;
+			f.write_f32::<LittleEndian>(self.line_height)?;
+		}
+		if version >= 7 {
+			f.write_u8(self.render_mode.as_u8())?;
+			f.write_all(&[0, 0, 0])?; // reserved
+		}
 
 		for g in &self.glyphs {
-			f.write_u32::<LittleEndian>(g.codepoint as u32).unwrap();
+			f.write_u32::<LittleEndian>(g.codepoint).unwrap();
 		}
 
 		for g in &self.glyphs {
@@ -412,9 +1334,24 @@ impl Font {
 			}
 			f.write_f32::<LittleEndian>(g.advance as f32).unwrap();
 			f.write_f32::<LittleEndian>(g.y_offset as f32).unwrap();
+			if version >= 3 {
+				f.write_u16::<LittleEndian>(g.page).unwrap();
+			}
 		}
 
-		Ok(1)
+		if version >= 5 {
+			// sorted, to keep the output stable
+			let mut pairs: Vec<_> = self.kerning.iter().collect();
+			pairs.sort_by(|a, b| a.0.cmp(b.0));
+			f.write_u32::<LittleEndian>(pairs.len() as u32)?;
+			for ((first, second), kerning) in pairs {
+				f.write_u32::<LittleEndian>(*first)?;
+				f.write_u32::<LittleEndian>(*second)?;
+				f.write_f32::<LittleEndian>(*kerning)?;
+			}
+		}
+
+		Ok(version)
 	}
 
 	#[allow(dead_code)]
@@ -483,6 +1420,7 @@ impl Font {
 		Ok(1)
 	}
 
+	// see create_with_options for everything else
 	pub fn create(
 		output: &str,
 		texsize: u32,
@@ -490,70 +1428,158 @@ impl Font {
 		border: u32,
 		distancefield_scale: u16,
 		distancefield_max_distance: u16,
-		input: &Vec<&str>,
+		input: &[&str],
 	) -> anyhow::Result<u32> {
-		// load ttf
-		// :TODO: load all input fonts!
-		let mut f = match File::open(input[0]) {
-			Ok(f) => f,
-			Err(_) => anyhow::bail!("io"),
-		};
-
-		let mut buffer = Vec::new();
+		let options = FontOptions::default()
+			.with_texsize(texsize)
+			.with_size(size)
+			.with_border(border)
+			.with_distancefield_scale(distancefield_scale)
+			.with_distancefield_max_distance(distancefield_max_distance);
+		Font::create_with_options(output, &options, input)
+	}
 
-		// read the whole file
-		f.read_to_end(&mut buffer).unwrap(); //_or_else( anyhow::bail!( "Error reading font file");
+	pub fn create_with_options(
+		output: &str,
+		options: &FontOptions,
+		input: &[&str],
+	) -> anyhow::Result<u32> {
+		if input.is_empty() {
+			anyhow::bail!("No input font given");
+		}
+		// load fonts, later ones are used for glyphs missing from earlier ones
+		let mut buffers = Vec::new();
+		for filename in input {
+			let mut buffer = Vec::new();
+			if !BitmapFont::is_bitmap_font(filename) {
+				let mut f = match File::open(filename) {
+					Ok(f) => f,
+					Err(e) => anyhow::bail!("Error opening {}: {}", filename, e),
+				};
+				f.read_to_end(&mut buffer)?;
+			}
+			buffers.push(buffer);
+		}
+		let mut sources = Vec::new();
+		for (filename, buffer) in input.iter().zip(buffers.iter()) {
+			sources.push(GlyphSource::load(filename, buffer, options.face_index)?);
+		}
+		// applied to every font with the axis
+		for (tag, value) in &options.variations {
+			let mut found = false;
+			for source in &mut sources {
+				found |= source.set_variation(tag, *value);
+			}
+			let name = String::from_utf8_lossy(tag).trim_end().to_string();
+			if !found {
+				let axes: Vec<String> = sources.iter().map(|s| s.axes()).collect();
+				let axes = axes.join(", ");
+				if axes.is_empty() {
+					anyhow::bail!(
+						"Unknown axis {}, the input fonts are not variable fonts",
+						name
+					);
+				}
+				anyhow::bail!("Unknown axis {}, available axes are {}", name, axes);
+			}
+			println!("Using {}={}", name, value);
+		}
 
-		let font = FontRef::try_from_slice(&buffer[..]).unwrap_or_else(|e| {
-			panic!("error constructing a Font from bytes: {}", e);
-		});
-		/*
-		let collection = FontCollection::from_bytes(&buffer[..] as &[u8]).unwrap_or_else(|e| {
-			panic!("error constructing a FontCollection from bytes: {}", e);
-		});
+		let texsize = options.texsize;
+		if !options.effects.is_empty()
+			&& (options.render_mode != RenderMode::DistanceField
+				|| options.distancefield_scale == 0)
+		{
+			anyhow::bail!(
+				"Effects need a distance field, use --mode sdf with a distancefield scale"
+			);
+		}
+		// make room for the effects
+		let border = options.border + options.effects.padding();
+		let size = match &sources[0] {
+			GlyphSource::Bitmap(font) if font.pixel_size > 0 => {
+				println!("Using pixel size {} of bitmap font", font.pixel_size);
+				font.pixel_size
+			},
+			_ => options.size,
+		};
 
-		let font = collection
-			.into_font() // only succeeds if collection consists of one font
-			.unwrap_or_else(|e| {
-				panic!("error turning FontCollection into a Font: {}", e);
-			});
-		*/
-		let scale = PxScale::from(size as f32); // :TODO: rusttype's understanding of size is different to our's!
-		let start = point(0.0, 0.0 /*+ v_metrics.ascent*/);
+		// every source is scaled on its own, so fallback glyphs match in size
+		let size_mode = match &sources[0] {
+			GlyphSource::Bitmap(_) if options.size_mode != SizeMode::Scale => {
+				println!("Ignoring size mode {} for bitmap font", options.size_mode);
+				SizeMode::Scale
+			},
+			_ => options.size_mode,
+		};
+		let mut scales = Vec::new();
+		for source in &sources {
+			scales.push(source.px_scale(size_mode, size)?);
+		}
 
 		let mut the_font = Font::new(texsize, size, border);
+		the_font.size_mode = size_mode;
+		the_font.render_mode = options.render_mode;
+		(the_font.em_size, the_font.line_height) = sources[0].metrics(scales[0]);
+		if size_mode != SizeMode::Scale {
+			println!(
+				"Using scale {:.2} for {} size {}, em size {:.2}, line height {:.2}",
+				scales[0], size_mode, size, the_font.em_size, the_font.line_height
+			);
+		}
+		if !options.effects.is_empty() {
+			the_font.effects = Some((
+				options.effects.clone(),
+				options.distancefield_max_distance as f32,
+			));
+		}
+		// everything up to 127 by default, for compatibility
+		let codepoints = match &options.codepoints {
+			Some(codepoints) => {
+				let mut missing = Vec::new();
+				let mut codepoints = codepoints.clone();
+				codepoints.sort();
+				codepoints.dedup();
+				codepoints.retain(|cp| {
+					let found = char::from_u32(*cp)
+						.map(|ch| sources.iter().any(|s| s.has_glyph(ch)))
+						.unwrap_or(false);
+					if !found {
+						missing.push(*cp);
+					}
+					found
+				});
+				for cp in &missing {
+					let ch = char::from_u32(*cp).unwrap_or(char::REPLACEMENT_CHARACTER);
+					println!(
+						"Warning: {:?} U+{:04X} is missing from all input fonts",
+						ch, cp
+					);
+				}
+				codepoints
+			},
+			None => (0..128).collect(),
+		};
+
 		let mut cnt = 0;
-		for c in 0..128u8 {
+		for codepoint in codepoints.iter().copied() {
 			cnt += 1;
-			let codepoint = c;
-			//			let codepoint = 0x30;	// :HACK:
-			//			let g = font.glyph( codepoint as char );
-			//			let data = g.get_data();
-			//			println!("{:?} -> {:#?}", c, data );
+			let Some(ch) = char::from_u32(codepoint) else {
+				continue;
+			};
 
 			// :HACK: :TODO: rasterize after positioning into final image
-			let ch = codepoint as char;
-			let glyph_id = font.glyph_id(ch);
-			let next_glyph_id = font.glyph_id('#'); // Using '#' to match original two-character approach
-
-			// Get scaled font for metrics
-			let scaled_font = font.as_scaled(scale);
-			let advance = scaled_font.h_advance(glyph_id);
-			let kern = scaled_font.kern(glyph_id, next_glyph_id);
-			let total_advance = advance + kern;
-
-			// Get the glyph with scale and position for rendering
-			let glyph = glyph_id.with_scale_and_position(scale, start);
-
-			if let Some(outlined) = font.outline_glyph(glyph) {
-				let bounds = outlined.px_bounds();
-				let bb_width = (bounds.max.x - bounds.min.x) as u32;
-				let bb_height = (bounds.max.y - bounds.min.y) as u32;
+			let idx_source = GlyphSource::find(&sources, ch);
+			let source = &sources[idx_source];
+			let scale = scales[idx_source];
+			let total_advance = source.advance(ch, scale);
+
+			if let Some((bb_width, bb_height, bottom)) = source.bounds(ch, scale) {
 				let h = bb_height + 2 * border;
 				let w = bb_width + 2 * border;
 				let mut glyph = Glyph::new(codepoint, w, h);
-				let y_offset = bounds.max.y;
-				glyph.y_offset = y_offset / texsize as f32;
+				// in pixels for now, normalized once the final texsize is known
+				glyph.y_offset = bottom;
 				glyph.advance = total_advance as u16;
 				the_font.add_glyph(glyph);
 			} else {
@@ -565,31 +1591,94 @@ impl Font {
 		}
 		println!("CNT {:?}", cnt);
 
-		if !the_font.fit_glyphs() {
+		if options.kerning {
+			let chars: Vec<char> = codepoints
+				.iter()
+				.filter_map(|cp| char::from_u32(*cp))
+				.collect();
+			for first in &chars {
+				let idx_source = GlyphSource::find(&sources, *first);
+				for second in &chars {
+					// only pairs from the same font can be kerned
+					if idx_source != GlyphSource::find(&sources, *second) {
+						continue;
+					}
+					let kerning = sources[idx_source].kerning(*first, *second, scales[idx_source]);
+					if kerning != 0.0 {
+						the_font
+							.kerning
+							.insert((*first as u32, *second as u32), kerning);
+					}
+				}
+			}
+		}
+		if !the_font.kerning.is_empty() {
+			println!("Kerning pairs: {}", the_font.kerning.len());
+		}
+
+		if options.glyphs_only {
+			// glyph images, and metrics in pixels, to be placed into a shared atlas by omt-atlas
+			for (idx, glyph_image) in the_font.render_glyphs(&sources, &scales, options) {
+				let filename = Font::glyph_image_filename(output, the_font.glyphs[idx].codepoint);
+				if let Some(parent) = Path::new(&filename).parent() {
+					std::fs::create_dir_all(parent)?;
+				}
+				glyph_image.save_with_format(&filename, ImageFormat::Png)?;
+			}
+			println!("Wrote glyph images to {}-glyphs", output);
+			the_font.recalc_matrix(1);
+
+			let filename = format!("{}.omfont", output);
+			println!("Writing font data to {}", filename);
+			the_font.save_omfont_v2(&filename, 0)?;
+			return Ok(0);
+		}
+
+		let mut page_count = the_font.fit_glyphs();
+		if let Some(maximum_texsize) = options.maximum_texsize {
+			// grow the texture first, and only use multiple pages when that is not enough
+			while page_count != 1 && the_font.texsize * 2 <= maximum_texsize {
+				the_font.texsize *= 2;
+				page_count = the_font.fit_glyphs();
+			}
+			println!("Using texsize: {}", the_font.texsize);
+		}
+		if page_count == 0 {
 			anyhow::bail!("Failed to fit glyphs into texture");
 		}
-		if !the_font.blit_glyphs(font, distancefield_scale, distancefield_max_distance) {
+		if page_count > 1 {
+			println!("Need {} pages to fit glyphs", page_count);
+		}
+		let texsize = the_font.texsize;
+		the_font.pages = (0..page_count)
+			.map(|_| image::DynamicImage::new_rgba8(texsize, texsize))
+			.collect();
+		for g in &mut the_font.glyphs {
+			g.y_offset /= texsize as f32;
+		}
+
+		if !the_font.blit_glyphs(&sources, &scales, options) {
 			anyhow::bail!("Failed to blitting glyphs into texture");
 		}
 		the_font.recalc_matrix(texsize);
 		//		println!("the font: {:#?}", the_font );
 
-		let filename = format!("{}.png", output);
-		println!("Writing texture to {}", filename);
-		the_font
-			.image
-			.save_with_format(filename, ImageFormat::Png)
-			.unwrap();
+		for (n, page) in the_font.pages.iter().enumerate() {
+			let filename = if page_count == 1 {
+				format!("{}.png", output)
+			} else {
+				format!("{}-{}.png", output, n)
+			};
+			println!("Writing texture to {}", filename);
+			if let Err(e) = page.save_with_format(&filename, ImageFormat::Png) {
+				anyhow::bail!("Error writing texture {}: {}", filename, e);
+			}
+		}
 
 		let filename = format!("{}.omfont", output);
 		println!("Writing font data to {}", filename);
 		//		match the_font.save_omfont( &filename ) {
-		match the_font.save_omfont_v2(&filename) {
-			Ok(_) => {},
-			Err(e) => {
-				println!("Error writing font data {:?}", e);
-			},
-		}
+		the_font.save_omfont_v2(&filename, page_count)?;
 
 		Ok(0)
 	}
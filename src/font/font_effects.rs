@@ -0,0 +1,184 @@
+use std::io::{Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+
+// Effects baked into the distance field font texture.
+// When any effect is enabled the channels are used as
+//   r: fill, as signed distance field
+//   g: outline, as signed distance field of the dilated glyph
+//   b: shadow, as (soft) coverage
+//   a: glow, as linear falloff
+// All sizes are in texels of the final texture.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FontEffects {
+	pub outline_width: f32,
+	pub outline_color: u32,
+	pub shadow_offset: (f32, f32),
+	pub shadow_blur:   f32,
+	pub shadow_color:  u32,
+	pub glow_radius:   f32,
+	pub glow_color:    u32,
+}
+
+impl FontEffects {
+	const FLAG_OUTLINE: u8 = 1 << 0;
+	const FLAG_SHADOW: u8 = 1 << 1;
+	const FLAG_GLOW: u8 = 1 << 2;
+
+	// colors are 0xRRGGBBAA
+	pub fn parse_color(s: &str) -> Result<u32, String> {
+		let hex = s.trim_start_matches('#');
+		let color = match u32::from_str_radix(hex, 16) {
+			Ok(c) => c,
+			Err(_) => return Err(format!("Invalid color {:?}, use RRGGBB, or RRGGBBAA", s)),
+		};
+		match hex.len() {
+			6 => Ok((color << 8) | 0xff),
+			8 => Ok(color),
+			_ => Err(format!("Invalid color {:?}, use RRGGBB, or RRGGBBAA", s)),
+		}
+	}
+
+	pub fn parse_offset(s: &str) -> Result<(f32, f32), String> {
+		let mut parts = s.split(',').map(|p| p.trim().parse::<f32>());
+		match (parts.next(), parts.next(), parts.next()) {
+			(Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+			_ => Err(format!("Invalid offset {:?}, use x,y", s)),
+		}
+	}
+
+	pub fn with_outline(mut self, width: f32, color: u32) -> Self {
+		self.outline_width = width;
+		self.outline_color = color;
+		self
+	}
+	pub fn with_shadow(mut self, offset: (f32, f32), blur: f32, color: u32) -> Self {
+		self.shadow_offset = offset;
+		self.shadow_blur = blur;
+		self.shadow_color = color;
+		self
+	}
+	pub fn with_glow(mut self, radius: f32, color: u32) -> Self {
+		self.glow_radius = radius;
+		self.glow_color = color;
+		self
+	}
+
+	pub fn has_outline(&self) -> bool {
+		self.outline_width > 0.0
+	}
+	pub fn has_shadow(&self) -> bool {
+		self.shadow_offset != (0.0, 0.0) || self.shadow_blur > 0.0
+	}
+	pub fn has_glow(&self) -> bool {
+		self.glow_radius > 0.0
+	}
+	pub fn is_empty(&self) -> bool {
+		!self.has_outline() && !self.has_shadow() && !self.has_glow()
+	}
+
+	fn flags(&self) -> u8 {
+		let mut flags = 0;
+		if self.has_outline() {
+			flags |= FontEffects::FLAG_OUTLINE;
+		}
+		if self.has_shadow() {
+			flags |= FontEffects::FLAG_SHADOW;
+		}
+		if self.has_glow() {
+			flags |= FontEffects::FLAG_GLOW;
+		}
+		flags
+	}
+
+	// extra space needed around each glyph
+	pub fn padding(&self) -> u32 {
+		let shadow = if self.has_shadow() {
+			self.shadow_offset.0.abs().max(self.shadow_offset.1.abs()) + self.shadow_blur
+		} else {
+			0.0
+		};
+		self.outline_width.max(self.glow_radius).max(shadow).ceil() as u32
+	}
+
+	// range the distance field needs to cover, at least the one of the fill
+	pub fn distance_range(&self, fill_range: f32) -> f32 {
+		let mut range = fill_range;
+		if self.has_outline() {
+			range = range.max(self.outline_width + fill_range);
+		}
+		if self.has_shadow() {
+			range = range.max(self.shadow_blur.max(0.5));
+		}
+		if self.has_glow() {
+			range = range.max(self.glow_radius);
+		}
+		range
+	}
+
+	// distance is the signed distance in texels, positive inside
+	pub fn encode(
+		&self,
+		distance: impl Fn(f32, f32) -> f32,
+		x: f32,
+		y: f32,
+		fill_range: f32,
+	) -> [u8; 4] {
+		let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+		let d = distance(x, y);
+
+		let fill = 0.5 + d / (2.0 * fill_range);
+		let outline = if self.has_outline() {
+			0.5 + (d + self.outline_width) / (2.0 * fill_range)
+		} else {
+			0.0
+		};
+		let shadow = if self.has_shadow() {
+			let ds = distance(x - self.shadow_offset.0, y - self.shadow_offset.1);
+			0.5 + ds / (2.0 * self.shadow_blur.max(0.5))
+		} else {
+			0.0
+		};
+		let glow = if self.has_glow() {
+			1.0 + d / self.glow_radius
+		} else {
+			0.0
+		};
+
+		[to_u8(fill), to_u8(outline), to_u8(shadow), to_u8(glow)]
+	}
+
+	// returns the effects, and the range of the fill distance field
+	pub fn read(r: &mut impl Read) -> anyhow::Result<(FontEffects, f32)> {
+		let _flags = r.read_u8()?;
+		let mut reserved = [0u8; 3];
+		r.read_exact(&mut reserved)?;
+		let fill_range = r.read_f32::<LittleEndian>()?;
+		let effects = FontEffects {
+			outline_width: r.read_f32::<LittleEndian>()?,
+			outline_color: r.read_u32::<LittleEndian>()?,
+			shadow_offset: (r.read_f32::<LittleEndian>()?, r.read_f32::<LittleEndian>()?),
+			shadow_blur:   r.read_f32::<LittleEndian>()?,
+			shadow_color:  r.read_u32::<LittleEndian>()?,
+			glow_radius:   r.read_f32::<LittleEndian>()?,
+			glow_color:    r.read_u32::<LittleEndian>()?,
+		};
+		Ok((effects, fill_range))
+	}
+
+	pub fn write(&self, w: &mut impl Write, fill_range: f32) -> anyhow::Result<()> {
+		w.write_u8(self.flags())?;
+		w.write_all(&[0, 0, 0])?; // reserved
+		w.write_f32::<LittleEndian>(fill_range)?;
+		w.write_f32::<LittleEndian>(self.outline_width)?;
+		w.write_u32::<LittleEndian>(self.outline_color)?;
+		w.write_f32::<LittleEndian>(self.shadow_offset.0)?;
+		w.write_f32::<LittleEndian>(self.shadow_offset.1)?;
+		w.write_f32::<LittleEndian>(self.shadow_blur)?;
+		w.write_u32::<LittleEndian>(self.shadow_color)?;
+		w.write_f32::<LittleEndian>(self.glow_radius)?;
+		w.write_u32::<LittleEndian>(self.glow_color)?;
+		Ok(())
+	}
+}
@@ -1,9 +1,10 @@
 use std::time::SystemTime;
 
-use image::GenericImageView;
-use minifb::{Key, Window, WindowOptions};
+use image::{DynamicImage, GenericImageView};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 
 use crate::font::Font;
+use crate::font::TextLayout;
 use crate::gfx::DrawBuffer;
 
 pub struct FontPreviewer {}
@@ -11,6 +12,12 @@ pub struct FontPreviewer {}
 const SIZE: usize = 1024;
 const WIDTH: usize = SIZE;
 const HEIGHT: usize = SIZE;
+// space around the text, and height of the zoom slider at the bottom
+const MARGIN: usize = 16;
+const SLIDER_HEIGHT: usize = 24;
+// zoom slider range, as powers of two
+const MIN_ZOOM_LOG2: f32 = -2.0;
+const MAX_ZOOM_LOG2: f32 = 3.0;
 //const GRID_SIZE: usize = 64;
 impl FontPreviewer {
 	pub fn preview(input: &str) -> anyhow::Result<u32> {
@@ -35,7 +42,7 @@ impl FontPreviewer {
 			let mut draw_buffer = DrawBuffer::new(WIDTH as u32, HEIGHT as u32);
 
 			let mut window = Window::new(
-				"omt-font - preview - ESC to exit",
+				"omt-font - preview - ESC to exit, LEFT/RIGHT to switch pages",
 				WIDTH,
 				HEIGHT,
 				WindowOptions::default(),
@@ -48,18 +55,27 @@ impl FontPreviewer {
 
 			grid_draw_buffer.fill_with_grid(64, 0xaaaaaaaa, 0x00000000);
 
+			let mut active_page = 0;
 			while window.is_open() && !window.is_key_down(Key::Escape) {
+				if window.is_key_pressed(Key::Right, KeyRepeat::No) {
+					active_page = (active_page + 1) % font.pages.len();
+				}
+				if window.is_key_pressed(Key::Left, KeyRepeat::No) {
+					active_page = (active_page + font.pages.len() - 1) % font.pages.len();
+				}
+				let image = &font.pages[active_page];
+
 				let now = SystemTime::now();
 				let time = now.duration_since(start_time).unwrap().as_millis();
 				let time = time as u128 as f32 / 1000.0;
 
-				let is = font.image.dimensions().0 as f32;
+				let is = image.dimensions().0 as f32;
 				scale = is / WIDTH as f32;
 				//				println!("New atlas scale: {:?}", scale );
 				img_draw_buffer.set_scale(scale);
 				draw_buffer.set_scale(scale);
 				img_draw_buffer.copy_from_draw_buffer(&grid_draw_buffer);
-				img_draw_buffer.blit_image(&font.image);
+				img_draw_buffer.blit_image(image);
 
 				/*
 								if prev_active_atlas != active_atlas {
@@ -94,14 +110,19 @@ impl FontPreviewer {
 				let frame_col = DrawBuffer::mix_rgba(0x808080ff, 0x802080ff, m);
 				let baseline_col = DrawBuffer::mix_rgba(0xffffffff, 0xe0e020ff, m);
 				draw_buffer.copy_from_draw_buffer(&img_draw_buffer);
-				for g in &font.glyphs {
+				let glyphs_on_page = font
+					.glyphs
+					.iter()
+					.filter(|g| g.page as usize == active_page)
+					.collect::<Vec<_>>();
+				for g in &glyphs_on_page {
 					let bs = (5.0 * draw_buffer.get_scale()).trunc() as u32;
 					draw_buffer
 						.draw_frame(g.x as i32, g.y as i32, g.width, g.height, frame_col, bs);
 				}
-				for g in &font.glyphs {
+				for g in &glyphs_on_page {
 					let bs = (2.0 * draw_buffer.get_scale()).trunc() as u32;
-					let y_offset = g.y_offset * font.image.dimensions().1 as f32;
+					let y_offset = g.y_offset * image.dimensions().1 as f32;
 					println!("{:?} - {:?}", g.height, y_offset);
 					let h = g.height as f32;
 					let h = h - y_offset;
@@ -124,4 +145,162 @@ impl FontPreviewer {
 			Ok(0)
 		}
 	}
+	// renders the sample text with the font texture, to tune the distance field visually
+	//   UP/DOWN threshold, LEFT/RIGHT softness, +/-, mouse wheel, or the slider at the bottom zoom
+	//   hovering a glyph shows its metrics in the title
+	pub fn preview_text(input: &str, text: &str) -> anyhow::Result<u32> {
+		let font = Font::load(input)?;
+
+		let mut window = Window::new(
+			"omt-font - text preview - ESC to exit",
+			WIDTH,
+			HEIGHT,
+			WindowOptions::default(),
+		)
+		.unwrap_or_else(|e| {
+			panic!("{}", e);
+		});
+		window.set_target_fps(60);
+
+		let mut grid_draw_buffer = DrawBuffer::new(WIDTH as u32, HEIGHT as u32);
+		let mut text_draw_buffer = DrawBuffer::new(WIDTH as u32, HEIGHT as u32);
+		let mut draw_buffer = DrawBuffer::new(WIDTH as u32, HEIGHT as u32);
+		grid_draw_buffer.fill_with_grid(64, 0x00303030, 0x00202020);
+
+		let mut threshold = 0.5f32;
+		let mut softness = 1.0f32;
+		let mut zoom_log2 = 0.0f32;
+		let mut layout = TextLayout::default();
+		let mut block = layout.layout(&font, text);
+		let mut dirty = true;
+		let mut title = String::new();
+
+		while window.is_open() && !window.is_key_down(Key::Escape) {
+			if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+				threshold = (threshold + 0.01).min(1.0);
+				dirty = true;
+			}
+			if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+				threshold = (threshold - 0.01).max(0.0);
+				dirty = true;
+			}
+			if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
+				softness += 0.1;
+				dirty = true;
+			}
+			if window.is_key_pressed(Key::Left, KeyRepeat::Yes) {
+				softness = (softness - 0.1).max(0.0);
+				dirty = true;
+			}
+			let mut zoom_change = 0.0;
+			if window.is_key_pressed(Key::Equal, KeyRepeat::Yes)
+				|| window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes)
+			{
+				zoom_change += 0.25;
+			}
+			if window.is_key_pressed(Key::Minus, KeyRepeat::Yes)
+				|| window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes)
+			{
+				zoom_change -= 0.25;
+			}
+			if let Some((_, wheel)) = window.get_scroll_wheel() {
+				// signum() is 1.0 for 0.0, so ignore horizontal only scrolling
+				if wheel != 0.0 {
+					zoom_change += wheel.signum() * 0.125;
+				}
+			}
+			let mouse = window.get_mouse_pos(MouseMode::Clamp);
+			if let Some((mx, my)) = mouse {
+				if window.get_mouse_down(MouseButton::Left) && my >= (HEIGHT - SLIDER_HEIGHT) as f32
+				{
+					let f = mx / WIDTH as f32;
+					let z = MIN_ZOOM_LOG2 + f * (MAX_ZOOM_LOG2 - MIN_ZOOM_LOG2);
+					zoom_change += z - zoom_log2;
+				}
+			}
+			if zoom_change != 0.0 {
+				zoom_log2 = (zoom_log2 + zoom_change).clamp(MIN_ZOOM_LOG2, MAX_ZOOM_LOG2);
+				dirty = true;
+			}
+
+			if dirty {
+				dirty = false;
+				layout = TextLayout::default()
+					.with_max_width((WIDTH - 2 * MARGIN) as f32)
+					.with_scale(zoom_log2.exp2())
+					.with_threshold(threshold)
+					.with_softness(softness);
+				block = layout.layout(&font, text);
+				let img = layout.render(&font, text, 0xffffffff, 0x00000000)?;
+				// the draw buffer needs an image covering all of it
+				let mut canvas = DynamicImage::new_rgba8(WIDTH as u32, HEIGHT as u32);
+				image::imageops::overlay(&mut canvas, &img, MARGIN as i64, MARGIN as i64);
+				text_draw_buffer.copy_from_draw_buffer(&grid_draw_buffer);
+				text_draw_buffer.blit_image(&canvas);
+			}
+			draw_buffer.copy_from_draw_buffer(&text_draw_buffer);
+
+			// glyph under the mouse
+			let mut hover_info = String::new();
+			if let Some((mx, my)) = mouse {
+				let (x, y) = (mx - MARGIN as f32, my - MARGIN as f32);
+				let hovered = block
+					.quads
+					.iter()
+					.find(|q| x >= q.x && x < q.x + q.width && y >= q.y && y < q.y + q.height);
+				if let Some(q) = hovered {
+					draw_buffer.draw_frame(
+						(q.x as usize + MARGIN) as i32,
+						(q.y as usize + MARGIN) as i32,
+						q.width as u32,
+						q.height as u32,
+						0x00e0e020,
+						2,
+					);
+					if let Some(g) = font.glyph(q.codepoint) {
+						let c = char::from_u32(g.codepoint).unwrap_or(' ');
+						hover_info = format!(
+							" - U+{:04X} {:?} advance {} y_offset {:.6}",
+							g.codepoint, c, g.advance, g.y_offset
+						);
+					}
+				}
+			}
+
+			// zoom slider
+			let slider_y = (HEIGHT - SLIDER_HEIGHT) as i32;
+			draw_buffer.draw_filled_rectangle(0, slider_y, WIDTH as i32, HEIGHT as i32, 0x00404040);
+			let f = (zoom_log2 - MIN_ZOOM_LOG2) / (MAX_ZOOM_LOG2 - MIN_ZOOM_LOG2);
+			let knob_x = (f * WIDTH as f32) as i32;
+			draw_buffer.draw_filled_rectangle(
+				knob_x - 4,
+				slider_y,
+				knob_x + 4,
+				HEIGHT as i32,
+				0x00e0e020,
+			);
+
+			let new_title = format!(
+				"omt-font - threshold {:.2} softness {:.1} zoom {:.2}x{}",
+				threshold,
+				softness,
+				zoom_log2.exp2(),
+				hover_info
+			);
+			if new_title != title {
+				window.set_title(&new_title);
+				title = new_title;
+			}
+
+			window
+				.update_with_buffer(
+					draw_buffer.get_data(),
+					draw_buffer.get_width() as usize,
+					draw_buffer.get_height() as usize,
+				)
+				.unwrap();
+		}
+
+		Ok(0)
+	}
 }
@@ -1,5 +1,21 @@
 mod font;
 pub use font::Font;
+pub use font::FontInfo;
+pub use font::FontOptions;
+pub use font::RenderMode;
+pub use font::SizeMode;
+
+mod font_effects;
+pub use font_effects::FontEffects;
+
+mod text_layout;
+pub use text_layout::{GlyphQuad, TextAlign, TextBlock, TextLayout};
+
+mod codepoint_collector;
+pub use codepoint_collector::CodepointCollector;
+
+mod bitmap_font;
+pub use bitmap_font::BitmapFont;
 
 mod fontpreviewer;
 pub use fontpreviewer::FontPreviewer;
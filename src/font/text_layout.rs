@@ -0,0 +1,398 @@
+use std::str::FromStr;
+
+use image::{DynamicImage, GenericImage, GenericImageView};
+
+use crate::font::Font;
+use crate::font::RenderMode;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TextAlign {
+	#[default]
+	Left,
+	Center,
+	Right,
+}
+
+impl FromStr for TextAlign {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"left" => Ok(TextAlign::Left),
+			"center" => Ok(TextAlign::Center),
+			"right" => Ok(TextAlign::Right),
+			a => Err(format!(
+				"Unknown alignment {:?}, use one of left, center, right",
+				a
+			)),
+		}
+	}
+}
+
+// a glyph placed in the text, in pixels with y down, and the top left of the text at 0,0
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlyphQuad {
+	pub codepoint: u32,
+	pub page:      u16,
+	pub x:         f32,
+	pub y:         f32,
+	pub width:     f32,
+	pub height:    f32,
+	pub uv:        [f32; 4], // left, top, right, bottom
+}
+
+#[derive(Debug, Default)]
+pub struct TextBlock {
+	pub quads:  Vec<GlyphQuad>,
+	pub width:  f32,
+	pub height: f32,
+	pub lines:  u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+	max_width:   Option<f32>,
+	align:       TextAlign,
+	line_height: Option<f32>,
+	scale:       f32,
+	threshold:   f32,
+	softness:    f32,
+}
+
+impl Default for TextLayout {
+	fn default() -> Self {
+		Self {
+			max_width:   None,
+			align:       TextAlign::default(),
+			line_height: None,
+			scale:       1.0,
+			threshold:   0.5,
+			softness:    1.0,
+		}
+	}
+}
+
+impl TextLayout {
+	// in pixels after scaling
+	pub fn with_max_width(mut self, max_width: f32) -> Self {
+		self.max_width = Some(max_width);
+		self
+	}
+	pub fn with_align(mut self, align: TextAlign) -> Self {
+		self.align = align;
+		self
+	}
+	// in pixels of the font, defaults to the line height stored in the font
+	pub fn with_line_height(mut self, line_height: f32) -> Self {
+		self.line_height = Some(line_height);
+		self
+	}
+	pub fn with_scale(mut self, scale: f32) -> Self {
+		self.scale = scale;
+		self
+	}
+	// distance field value of the edge, 0.5 matches the glyph outline
+	pub fn with_threshold(mut self, threshold: f32) -> Self {
+		self.threshold = threshold;
+		self
+	}
+	// width of the antialiased edge, 1.0 is about one pixel, 0.0 is hard
+	pub fn with_softness(mut self, softness: f32) -> Self {
+		self.softness = softness;
+		self
+	}
+
+	// advance including the kerning to the next character
+	fn advance(font: &Font, c: char, next: Option<&char>) -> f32 {
+		let advance = match font.glyph(c as u32) {
+			Some(g) => g.advance as f32,
+			None => 0.0,
+		};
+		let kerning = match next {
+			Some(n) => font.kerning(c as u32, *n as u32),
+			None => 0.0,
+		};
+		advance + kerning
+	}
+
+	fn line_width(font: &Font, line: &[char]) -> f32 {
+		let end = line.iter().rposition(|c| *c != ' ').map_or(0, |e| e + 1);
+		let line = &line[..end];
+		line.iter()
+			.enumerate()
+			.map(|(i, c)| TextLayout::advance(font, *c, line.get(i + 1)))
+			.sum()
+	}
+
+	// greedy word wrap, breaks inside of words only when a single word doesn't fit
+	fn wrap(&self, font: &Font, paragraph: &[char]) -> Vec<Vec<char>> {
+		let max_width = match self.max_width {
+			Some(max_width) => max_width / self.scale,
+			None => return vec![paragraph.to_vec()],
+		};
+
+		let mut lines = Vec::new();
+		let mut start = 0;
+		while start < paragraph.len() {
+			let mut end = start;
+			let mut width = 0.0;
+			let mut last_space = None;
+			while end < paragraph.len() {
+				let c = paragraph[end];
+				let w = width + TextLayout::advance(font, c, paragraph.get(end + 1));
+				if c != ' ' && w > max_width && end > start {
+					break;
+				}
+				if c == ' ' {
+					last_space = Some(end);
+				}
+				width = w;
+				end += 1;
+			}
+			match last_space {
+				Some(space) if end < paragraph.len() && space > start => {
+					lines.push(paragraph[start..space].to_vec());
+					start = space + 1;
+				},
+				_ => {
+					lines.push(paragraph[start..end].to_vec());
+					start = end;
+				},
+			}
+			// no leading spaces on wrapped lines
+			while start < paragraph.len() && paragraph[start] == ' ' {
+				start += 1;
+			}
+		}
+		if lines.is_empty() {
+			lines.push(Vec::new());
+		}
+		lines
+	}
+
+	pub fn layout(&self, font: &Font, text: &str) -> TextBlock {
+		let texsize = font.texsize() as f32;
+		let line_height = self.line_height.unwrap_or(font.line_height());
+
+		// distance from the top of the line to the baseline, and from the baseline to the bottom
+		let (ascent, descent) = font.glyphs.iter().fold((0.0f32, 0.0f32), |(a, d), g| {
+			let bottom = g.y_offset * texsize;
+			(a.max(g.height as f32 - bottom), d.max(bottom))
+		});
+
+		let mut lines = Vec::new();
+		for paragraph in text.split('\n') {
+			let paragraph: Vec<char> = paragraph.trim_end_matches('\r').chars().collect();
+			lines.append(&mut self.wrap(font, &paragraph));
+		}
+
+		let widths: Vec<f32> = lines
+			.iter()
+			.map(|l| TextLayout::line_width(font, l))
+			.collect();
+		let width = widths.iter().cloned().fold(0.0, f32::max);
+
+		let mut block = TextBlock {
+			quads:  Vec::new(),
+			width:  width * self.scale,
+			height: ((lines.len() - 1) as f32 * line_height + ascent + descent) * self.scale,
+			lines:  lines.len() as u32,
+		};
+
+		for (line_index, line) in lines.iter().enumerate() {
+			let baseline = line_index as f32 * line_height + ascent;
+			let mut x = match self.align {
+				TextAlign::Left => 0.0,
+				TextAlign::Center => (width - widths[line_index]) * 0.5,
+				TextAlign::Right => width - widths[line_index],
+			};
+			for (i, c) in line.iter().enumerate() {
+				let Some(g) = font.glyph(*c as u32) else {
+					continue;
+				};
+				if g.width > 0 && g.height > 0 {
+					let m = g.matrix;
+					let bottom = baseline + g.y_offset * texsize;
+					block.quads.push(GlyphQuad {
+						codepoint: *c as u32,
+						page:      g.page,
+						x:         x * self.scale,
+						y:         (bottom - g.height as f32) * self.scale,
+						width:     g.width as f32 * self.scale,
+						height:    g.height as f32 * self.scale,
+						uv:        [m[2], m[5], m[2] + m[0], m[5] + m[4]],
+					});
+				}
+				x += TextLayout::advance(font, *c, line.get(i + 1));
+			}
+		}
+
+		block
+	}
+
+	// composites the text, colors are 0xRRGGBBAA, as the font was rendered
+	pub fn render(
+		&self,
+		font: &Font,
+		text: &str,
+		color: u32,
+		background: u32,
+	) -> anyhow::Result<DynamicImage> {
+		if font.pages.is_empty() {
+			anyhow::bail!("Font has no texture to render from");
+		}
+		let block = self.layout(font, text);
+
+		let w = (block.width.ceil() as u32).max(1);
+		let h = (block.height.ceil() as u32).max(1);
+		let mut img = DynamicImage::new_rgba8(w, h);
+		let background = rgba(background);
+		for y in 0..h {
+			for x in 0..w {
+				img.put_pixel(x, y, to_pixel(background));
+			}
+		}
+
+		// half the change of the distance field value across one output pixel
+		let range = font.distance_range().unwrap_or(2.0);
+		let edge = (0.5 / (2.0 * range * self.scale) * self.softness).max(f32::EPSILON);
+		let coverage = |v: f32| smoothstep(self.threshold - edge, self.threshold + edge, v);
+
+		let render_mode = font.render_mode();
+		// back to front
+		let mut layers: Vec<(usize, [f32; 4], bool)> = Vec::new();
+		match render_mode {
+			RenderMode::DistanceField => {
+				if let Some(effects) = font.effects() {
+					if effects.has_glow() {
+						layers.push((3, rgba(effects.glow_color), false));
+					}
+					if effects.has_shadow() {
+						layers.push((2, rgba(effects.shadow_color), false));
+					}
+					if effects.has_outline() {
+						layers.push((1, rgba(effects.outline_color), true));
+					}
+				}
+				layers.push((0, rgba(color), true));
+			},
+			_ => layers.push((3, rgba(color), false)),
+		}
+
+		let texsize = font.texsize() as f32;
+		for q in &block.quads {
+			let page = &font.pages[q.page as usize];
+			let x0 = q.x.floor().max(0.0) as u32;
+			let y0 = q.y.floor().max(0.0) as u32;
+			let x1 = ((q.x + q.width).ceil() as u32).min(w);
+			let y1 = ((q.y + q.height).ceil() as u32).min(h);
+			for y in y0..y1 {
+				for x in x0..x1 {
+					let fx = (x as f32 + 0.5 - q.x) / q.width;
+					let fy = (y as f32 + 0.5 - q.y) / q.height;
+					if !(0.0..=1.0).contains(&fx) || !(0.0..=1.0).contains(&fy) {
+						continue;
+					}
+					let u = (q.uv[0] + fx * (q.uv[2] - q.uv[0])) * texsize;
+					let v = (q.uv[1] + fy * (q.uv[3] - q.uv[1])) * texsize;
+					let texel = if render_mode == RenderMode::Bitmap {
+						sample_nearest(page, u, v)
+					} else {
+						sample_bilinear(page, u, v)
+					};
+
+					let mut dst = rgba_from_pixel(img.get_pixel(x, y));
+					for (channel, layer_color, is_distance) in &layers {
+						let a = if *is_distance {
+							coverage(texel[*channel])
+						} else {
+							texel[*channel]
+						};
+						dst = over(
+							[
+								layer_color[0],
+								layer_color[1],
+								layer_color[2],
+								layer_color[3] * a,
+							],
+							dst,
+						);
+					}
+					img.put_pixel(x, y, to_pixel(dst));
+				}
+			}
+		}
+
+		Ok(img)
+	}
+}
+
+fn smoothstep(e0: f32, e1: f32, v: f32) -> f32 {
+	let t = ((v - e0) / (e1 - e0)).clamp(0.0, 1.0);
+	t * t * (3.0 - 2.0 * t)
+}
+
+fn rgba(color: u32) -> [f32; 4] {
+	[
+		((color >> 24) & 0xff) as f32 / 255.0,
+		((color >> 16) & 0xff) as f32 / 255.0,
+		((color >> 8) & 0xff) as f32 / 255.0,
+		(color & 0xff) as f32 / 255.0,
+	]
+}
+
+fn rgba_from_pixel(p: image::Rgba<u8>) -> [f32; 4] {
+	[
+		p[0] as f32 / 255.0,
+		p[1] as f32 / 255.0,
+		p[2] as f32 / 255.0,
+		p[3] as f32 / 255.0,
+	]
+}
+
+fn to_pixel(c: [f32; 4]) -> image::Rgba<u8> {
+	let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+	image::Rgba([to_u8(c[0]), to_u8(c[1]), to_u8(c[2]), to_u8(c[3])])
+}
+
+// straight alpha
+fn over(src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+	let a = src[3] + dst[3] * (1.0 - src[3]);
+	if a <= 0.0 {
+		return [0.0; 4];
+	}
+	let mut out = [0.0, 0.0, 0.0, a];
+	for i in 0..3 {
+		out[i] = (src[i] * src[3] + dst[i] * dst[3] * (1.0 - src[3])) / a;
+	}
+	out
+}
+
+fn sample_nearest(img: &DynamicImage, u: f32, v: f32) -> [f32; 4] {
+	let (w, h) = img.dimensions();
+	let x = (u.floor().max(0.0) as u32).min(w - 1);
+	let y = (v.floor().max(0.0) as u32).min(h - 1);
+	rgba_from_pixel(img.get_pixel(x, y))
+}
+
+fn sample_bilinear(img: &DynamicImage, u: f32, v: f32) -> [f32; 4] {
+	let (w, h) = img.dimensions();
+	let u = u - 0.5;
+	let v = v - 0.5;
+	let (x0, y0) = (u.floor(), v.floor());
+	let (fx, fy) = (u - x0, v - y0);
+	let texel = |x: f32, y: f32| {
+		let x = (x.max(0.0) as u32).min(w - 1);
+		let y = (y.max(0.0) as u32).min(h - 1);
+		rgba_from_pixel(img.get_pixel(x, y))
+	};
+	let (a, b) = (texel(x0, y0), texel(x0 + 1.0, y0));
+	let (c, d) = (texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+	let mut out = [0.0; 4];
+	for i in 0..4 {
+		let top = a[i] * (1.0 - fx) + b[i] * fx;
+		let bottom = c[i] * (1.0 - fx) + d[i] * fx;
+		out[i] = top * (1.0 - fy) + bottom * fy;
+	}
+	out
+}
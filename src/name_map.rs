@@ -67,7 +67,7 @@ impl NameMap {
 		}
 
 		let number_of_names = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
-		println!("Reading {:?} names from files", number_of_names);
+		eprintln!("Reading {:?} names from files", number_of_names);
 
 		for _e in 0..number_of_names {
 			let crc = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
@@ -91,7 +91,7 @@ impl NameMap {
 				nm.add_from_file(&mut file)?;
 			},
 			Err(_e) => {
-				println!("Couldn't open {}. Creating new one.", &filename);
+				eprintln!("Couldn't open {}. Creating new one.", &filename);
 				// anyhow::bail!("Error reading NameMap file {}", &filename)
 			},
 		};
@@ -121,7 +121,7 @@ impl NameMap {
 		let number_of_entries = self.entries.len() as u32;
 		file.write_u32::<LittleEndian>(number_of_entries).unwrap();
 
-		for entry in self.entries.values() {
+		for entry in self.sorted_entries() {
 			// crc, pos, size all as LittleEndian u32
 			file.write_u32::<LittleEndian>(entry.crc)?;
 			let l = entry.name.len();
@@ -156,6 +156,13 @@ impl NameMap {
 		self.entries.get(&crc).map(|e| &e.name)
 	}
 
+	// by crc, so saving doesn't depend on the HashMap order
+	fn sorted_entries(&self) -> Vec<&Entry> {
+		let mut entries: Vec<&Entry> = self.entries.values().collect();
+		entries.sort_by_key(|e| e.crc);
+		entries
+	}
+
 	pub fn dirty(&self) -> bool {
 		self.dirty
 	}
@@ -167,7 +174,7 @@ impl NameMap {
 
 impl core::fmt::Display for NameMap {
 	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
-		for e in self.entries.values() {
+		for e in self.sorted_entries() {
 			write!(f, "{}\n", e)?;
 		}
 		Ok(())
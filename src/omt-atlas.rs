@@ -25,9 +25,11 @@ enum Commands {
 		maximum_size:   Option<u32>,
 		#[arg(short, long, default_value_t = 0)]
 		border:         u32,
-		#[arg(short, long, num_args = 1.., required = true)]
+		#[arg(short, long, num_args = 1.., required_unless_present = "font")]
 		//		#[clap(short, long, required = true)] // use above, since this is not good enough
 		input: Vec<std::path::PathBuf>,
+		#[arg(long, num_args = 1.., help = "Add the glyphs of fonts created with `omt-font create --glyphs-only`")]
+		font:           Vec<std::path::PathBuf>,
 		#[arg(short = 'r', long)]
 		reference_path: Option<std::path::PathBuf>,
 	},
@@ -64,6 +66,7 @@ fn main() -> anyhow::Result<()> {
 					maximum_size,
 					border,
 					input,
+					font,
 					reference_path,
 				} => {
 					//println!("combine {:?} {} {} {:?}", &output, &size, &border, &input);
@@ -81,9 +84,13 @@ fn main() -> anyhow::Result<()> {
 						println!("\t{:?}", i);
 					}
 					println!("]");
+					if !font.is_empty() {
+						println!("font           : {:?}", font);
+					}
 					let mut atlas_set = AtlasSet::default()
 						.with_border(border)
-						.with_inputs(input.iter().map(|p| p.as_path()).collect());
+						.with_inputs(input.iter().map(|p| p.as_path()).collect())
+						.with_fonts(font.iter().map(|p| p.as_path()).collect());
 					if let Some(size) = &size {
 						atlas_set = atlas_set.with_target_size(*size);
 					};
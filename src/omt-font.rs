@@ -1,8 +1,16 @@
+use std::path::Path;
 use std::process;
 
 use clap::{Parser, Subcommand};
+use omt::font::CodepointCollector;
 use omt::font::Font;
+use omt::font::FontEffects;
+use omt::font::FontOptions;
 use omt::font::FontPreviewer;
+use omt::font::RenderMode;
+use omt::font::SizeMode;
+use omt::font::TextAlign;
+use omt::font::TextLayout;
 
 #[derive(Debug, Parser)]
 #[command(name = "omt-font")]
@@ -31,6 +39,13 @@ enum Commands {
 			default_value_t = 16
 		)]
 		size: u32,
+		#[arg(
+			long,
+			value_name = "SIZE-MODE",
+			help = "Set what the size measures (scale, em, cap-height, line-height)",
+			default_value = "scale"
+		)]
+		size_mode: SizeMode,
 		#[arg(
 			long,
 			value_name = "TEXSIZE",
@@ -38,6 +53,12 @@ enum Commands {
 			default_value_t = 1024
 		)]
 		texsize: u32,
+		#[arg(
+			long,
+			value_name = "MAXIMUM-TEXSIZE",
+			help = "Grow the texture up to this size before using multiple pages"
+		)]
+		maximum_texsize: Option<u32>,
 		#[arg(
 			long,
 			value_name = "BORDER",
@@ -59,7 +80,109 @@ enum Commands {
 			default_value_t = 2
 		)]
 		distancefield_max_distance: u16,
-		#[arg(long, value_name = "INPUT", help = "Set the input font(s) (only .ttf supported)", num_args = 1.., required = true)]
+		#[arg(
+			long,
+			value_name = "MODE",
+			help = "Set the render mode (sdf, bitmap, coverage, supersampled)",
+			default_value = "sdf"
+		)]
+		mode: RenderMode,
+		#[arg(
+			long,
+			value_name = "THRESHOLD",
+			help = "Set the coverage threshold for the 1-bit bitmap mode",
+			default_value_t = 128
+		)]
+		threshold: u8,
+		#[arg(
+			long,
+			value_name = "SUPERSAMPLE",
+			help = "Set the supersampling factor for the supersampled mode",
+			default_value_t = 4
+		)]
+		supersample: u16,
+		#[arg(
+			long,
+			value_name = "OUTLINE-WIDTH",
+			help = "Bake an outline of this width (in texels) into the distance field",
+			default_value_t = 0.0
+		)]
+		outline_width: f32,
+		#[arg(
+			long,
+			value_name = "OUTLINE-COLOR",
+			help = "Set the outline color (RRGGBB, or RRGGBBAA)",
+			default_value = "000000ff",
+			value_parser = FontEffects::parse_color
+		)]
+		outline_color: u32,
+		#[arg(
+			long,
+			value_name = "SHADOW-OFFSET",
+			help = "Bake a drop shadow with this offset (x,y in texels) into the distance field",
+			default_value = "0,0",
+			value_parser = FontEffects::parse_offset,
+			allow_hyphen_values = true
+		)]
+		shadow_offset: (f32, f32),
+		#[arg(
+			long,
+			value_name = "SHADOW-BLUR",
+			help = "Set the shadow blur (in texels)",
+			default_value_t = 0.0
+		)]
+		shadow_blur: f32,
+		#[arg(
+			long,
+			value_name = "SHADOW-COLOR",
+			help = "Set the shadow color (RRGGBB, or RRGGBBAA)",
+			default_value = "00000080",
+			value_parser = FontEffects::parse_color
+		)]
+		shadow_color: u32,
+		#[arg(
+			long,
+			value_name = "GLOW-RADIUS",
+			help = "Bake an outer glow with this radius (in texels) into the distance field",
+			default_value_t = 0.0
+		)]
+		glow_radius: f32,
+		#[arg(
+			long,
+			value_name = "GLOW-COLOR",
+			help = "Set the glow color (RRGGBB, or RRGGBBAA)",
+			default_value = "ffffffff",
+			value_parser = FontEffects::parse_color
+		)]
+		glow_color: u32,
+		#[arg(
+			long,
+			help = "Only write glyph images, and metrics, to be combined into a shared atlas with `omt-atlas combine --font`"
+		)]
+		glyphs_only: bool,
+		#[arg(long, help = "Store kerning pairs in the font data")]
+		kerning: bool,
+		#[arg(
+			long,
+			value_name = "TEXT-INPUT",
+			help = "Only create the glyphs used in these text files (.csv, .tsv, .json, .po, .yaml, or plain text)",
+			num_args = 1..
+		)]
+		text_input: Vec<String>,
+		#[arg(
+			long,
+			value_name = "FACE-INDEX",
+			help = "Set the face to use from .ttc, or .otc collections",
+			default_value_t = 0
+		)]
+		face_index: u32,
+		#[arg(
+			long,
+			value_name = "VARIATIONS",
+			help = "Set variable font axes, e.g. wght=700,wdth=90"
+		)]
+		variations: Option<String>,
+		#[arg(long, value_name = "INPUT", help = "Set the input font(s) (.ttf, .otf, .bdf, .pcf)", num_args = 1.., required = true)]
 		input: Vec<String>,
 	},
 	Preview {
@@ -70,6 +193,107 @@ enum Commands {
 			default_value = "output-font"
 		)]
 		input: String,
+		#[arg(
+			long,
+			value_name = "TEXT",
+			help = "Render this sample text instead of showing the texture, \\n for line breaks"
+		)]
+		text:  Option<String>,
+	},
+	Info {
+		#[arg(
+			long,
+			value_name = "INPUT",
+			help = "Set the input font, without .omfont"
+		)]
+		input:   String,
+		#[arg(
+			long,
+			value_name = "TEXSIZE",
+			help = "Set the texture size, if the texture is not next to the font"
+		)]
+		texsize: Option<u32>,
+		#[arg(long, help = "Print as JSON")]
+		json:    bool,
+	},
+	Convert {
+		#[arg(
+			long,
+			value_name = "INPUT",
+			help = "Set the input font, without .omfont"
+		)]
+		input:   String,
+		#[arg(
+			long,
+			value_name = "OUTPUT",
+			help = "Set the output font, without .omfont"
+		)]
+		output:  String,
+		#[arg(
+			long,
+			value_name = "TEXSIZE",
+			help = "Set the texture size, if the texture is not next to the font"
+		)]
+		texsize: Option<u32>,
+	},
+	Render {
+		#[arg(
+			long,
+			value_name = "INPUT",
+			help = "Set the input font",
+			default_value = "output-font"
+		)]
+		input:       String,
+		#[arg(
+			long,
+			value_name = "TEXT",
+			help = "Set the text to render, \\n for line breaks"
+		)]
+		text:        String,
+		#[arg(long, value_name = "OUTPUT", help = "Set the output image (.png)")]
+		output:      String,
+		#[arg(
+			long,
+			value_name = "MAX-WIDTH",
+			help = "Wrap words at this width (in pixels)"
+		)]
+		max_width:   Option<f32>,
+		#[arg(
+			long,
+			value_name = "ALIGN",
+			help = "Set the alignment (left, center, right)",
+			default_value = "left"
+		)]
+		align:       TextAlign,
+		#[arg(
+			long,
+			value_name = "LINE-HEIGHT",
+			help = "Set the line height (in pixels of the font), defaults to the line height of the font"
+		)]
+		line_height: Option<f32>,
+		#[arg(
+			long,
+			value_name = "SCALE",
+			help = "Set the scale",
+			default_value_t = 1.0
+		)]
+		scale:       f32,
+		#[arg(
+			long,
+			value_name = "COLOR",
+			help = "Set the text color (RRGGBB, or RRGGBBAA)",
+			default_value = "ffffffff",
+			value_parser = FontEffects::parse_color
+		)]
+		color:       u32,
+		#[arg(
+			long,
+			value_name = "BACKGROUND",
+			help = "Set the background color (RRGGBB, or RRGGBBAA)",
+			default_value = "00000000",
+			value_parser = FontEffects::parse_color
+		)]
+		background:  u32,
 	},
 }
 
@@ -83,18 +307,58 @@ fn main() {
 		Some(Commands::Create {
 			output,
 			size,
+			size_mode,
 			texsize,
+			maximum_texsize,
 			border,
 			distancefield_scale,
 			distancefield_max_distance,
+			mode,
+			threshold,
+			supersample,
+			outline_width,
+			outline_color,
+			shadow_offset,
+			shadow_blur,
+			shadow_color,
+			glow_radius,
+			glow_color,
+			glyphs_only,
+			kerning,
+			text_input,
+			face_index,
+			variations,
 			input,
 		}) => {
 			println!("output         : {:?}", output);
 			println!("texsize        : {:?}", texsize);
+			println!("maximum_texsize: {:?}", maximum_texsize);
 			println!("size           : {:?}", size);
+			println!("size_mode      : {}", size_mode);
 			println!("border         : {:?}", border);
 			println!("df_scale  	 : {:?}", distancefield_scale);
 			println!("df_max_distance: {:?}", distancefield_max_distance);
+			println!("mode           : {:?}", mode);
+			println!("threshold      : {:?}", threshold);
+			println!("supersample    : {:?}", supersample);
+			println!(
+				"outline        : {:?} {:#010x}",
+				outline_width, outline_color
+			);
+			println!(
+				"shadow         : {:?} {:?} {:#010x}",
+				shadow_offset, shadow_blur, shadow_color
+			);
+			println!("glow           : {:?} {:#010x}", glow_radius, glow_color);
+			println!("glyphs_only    : {:?}", glyphs_only);
+			println!("kerning        : {:?}", kerning);
+			if !text_input.is_empty() {
+				println!("text_input     : {:?}", text_input);
+			}
+			println!("face_index     : {:?}", face_index);
+			if let Some(variations) = &variations {
+				println!("variations     : {:?}", variations);
+			}
 			//		println!("input          : {:?}", input );
 			println!("input          : [");
 			for i in &input {
@@ -104,15 +368,50 @@ fn main() {
 
 			let input_refs: Vec<&str> = input.iter().map(String::as_str).collect();
 
-			match Font::create(
-				&output,
-				texsize,
-				size,
-				border,
-				distancefield_scale,
-				distancefield_max_distance,
-				&input_refs,
-			) {
+			let mut options = FontOptions::default()
+				.with_texsize(texsize)
+				.with_size(size)
+				.with_size_mode(size_mode)
+				.with_border(border)
+				.with_distancefield_scale(distancefield_scale)
+				.with_distancefield_max_distance(distancefield_max_distance)
+				.with_render_mode(mode)
+				.with_threshold(threshold)
+				.with_supersample(supersample)
+				.with_glyphs_only(glyphs_only)
+				.with_kerning(kerning)
+				.with_face_index(face_index)
+				.with_effects(
+					FontEffects::default()
+						.with_outline(outline_width, outline_color)
+						.with_shadow(shadow_offset, shadow_blur, shadow_color)
+						.with_glow(glow_radius, glow_color),
+				);
+			if let Some(maximum_texsize) = maximum_texsize {
+				options = options.with_maximum_texsize(maximum_texsize);
+			}
+			if let Some(variations) = &variations {
+				match FontOptions::parse_variations(variations) {
+					Ok(variations) => options = options.with_variations(variations),
+					Err(e) => {
+						println!("Error: {}", e);
+						process::exit(-1);
+					},
+				}
+			}
+			if !text_input.is_empty() {
+				let mut collector = CodepointCollector::default();
+				for t in &text_input {
+					if let Err(e) = collector.add_file(Path::new(t)) {
+						println!("Error collecting characters >{:?}<", e);
+						process::exit(-1);
+					}
+				}
+				println!("Found {} different characters", collector.len());
+				options = options.with_codepoints(collector.codepoints());
+			}
+
+			match Font::create_with_options(&output, &options, &input_refs) {
 				Ok(1) => {
 					println!("1 font created");
 					process::exit(0);
@@ -127,9 +426,13 @@ fn main() {
 				},
 			}
 		},
-		Some(Commands::Preview { input }) => {
+		Some(Commands::Preview { input, text }) => {
 			println!("input         : {:?}", input);
-			match FontPreviewer::preview(&input) {
+			let result = match text {
+				Some(text) => FontPreviewer::preview_text(&input, &text.replace("\\n", "\n")),
+				None => FontPreviewer::preview(&input),
+			};
+			match result {
 				Ok(_) => {
 					process::exit(0);
 				},
@@ -139,6 +442,113 @@ fn main() {
 				},
 			}
 		},
+		Some(Commands::Info {
+			input,
+			texsize,
+			json,
+		}) => match Font::info(&input, texsize) {
+			Ok(info) if json => match serde_json::to_string_pretty(&info) {
+				Ok(json) => {
+					println!("{}", json);
+					process::exit(0);
+				},
+				Err(e) => {
+					println!("Error converting font info to json: {}", &e);
+					process::exit(-1);
+				},
+			},
+			Ok(info) => {
+				println!("Font {}.omfont", input);
+				println!("\tFormat : {} v{}", info.format, info.version);
+				println!("\tSize   : {} ({})", info.size, info.size_mode);
+				println!("\tMode   : {}", info.render_mode);
+				println!(
+					"\tMetrics: em size {:.2}, line height {:.2}",
+					info.em_size, info.line_height
+				);
+				match info.texsize {
+					Some(texsize) => println!("\tTexsize: {}", texsize),
+					None => println!("\tTexsize: unknown"),
+				}
+				println!("\tPages  : {}", info.pages);
+				if let Some(effects) = &info.effects {
+					println!("\tEffects: {:?}", effects);
+				}
+				println!("\tKerning: {} pairs", info.kerning_pairs);
+				println!("\tGlyphs : {}", info.glyphs.len());
+				for g in &info.glyphs {
+					let c = char::from_u32(g.codepoint)
+						.filter(|c| !c.is_control())
+						.unwrap_or(' ');
+					println!(
+						"\t\t{:>5} {} {:>5} x {:>5}  @  {:>5},{:>5}  page {:>2}  advance {:>4}  y_offset {:>10.6}",
+						g.codepoint, c, g.width, g.height, g.x, g.y, g.page, g.advance, g.y_offset
+					);
+				}
+				process::exit(0);
+			},
+			Err(e) => {
+				println!("Error getting info from font: {}", &e);
+				process::exit(-1);
+			},
+		},
+		Some(Commands::Convert {
+			input,
+			output,
+			texsize,
+		}) => {
+			println!("input         : {:?}", input);
+			println!("output        : {:?}", output);
+			match Font::convert(&input, &output, texsize) {
+				Ok(version) => {
+					println!("Converted font to v{}", version);
+					process::exit(0);
+				},
+				Err(e) => {
+					println!("Error converting font: {}", &e);
+					process::exit(-1);
+				},
+			}
+		},
+		Some(Commands::Render {
+			input,
+			text,
+			output,
+			max_width,
+			align,
+			line_height,
+			scale,
+			color,
+			background,
+		}) => {
+			println!("input         : {:?}", input);
+			println!("text          : {:?}", text);
+			println!("output        : {:?}", output);
+
+			let mut layout = TextLayout::default().with_align(align).with_scale(scale);
+			if let Some(max_width) = max_width {
+				layout = layout.with_max_width(max_width);
+			}
+			if let Some(line_height) = line_height {
+				layout = layout.with_line_height(line_height);
+			}
+			let text = text.replace("\\n", "\n");
+			let result = Font::load(&input).and_then(|font| {
+				let img = layout.render(&font, &text, color, background)?;
+				img.save(&output)?;
+				Ok(img)
+			});
+			match result {
+				Ok(img) => {
+					println!("Rendered {}x{} to {}", img.width(), img.height(), output);
+					process::exit(0);
+				},
+				Err(e) => {
+					println!("Error rendering text >{:?}<", e);
+					process::exit(-1);
+				},
+			}
+		},
 		None => {
 			process::exit(-1);
 		},
@@ -2,9 +2,19 @@ use std::process;
 
 use clap::{Parser, Subcommand};
 use omt::packer::command_packer::CommandPacker;
+use omt::packer::command_packer_apply::CommandPackerApply;
+use omt::packer::command_packer_cat::CommandPackerCat;
+use omt::packer::command_packer_diff::CommandPackerDiff;
 use omt::packer::command_packer_list::CommandPackerList;
 use omt::packer::command_packer_pack::CommandPackerPack;
+use omt::packer::command_packer_stats::CommandPackerStats;
 use omt::packer::command_packer_unpack::CommandPackerUnpack;
+use omt::packer::command_packer_verify::CommandPackerVerify;
+use omt::packer::AlignRule;
+use omt::packer::EntryFilter;
+use omt::packer::EntryOrder;
+use omt::packer::NameHash;
+use omt::packer::UnpackLayout;
 
 #[derive(Debug, Parser)]
 #[command(name = "omt-packer")]
@@ -25,27 +35,79 @@ enum Commands {
 			required = true,
 			help = "Set the base path (for relative names)"
 		)]
-		basepath: String,
+		basepath:     String,
 		#[arg(
 			long,
 			value_name = "OUTPUT",
 			required = true,
 			help = "Set the output filename"
 		)]
-		output:   String,
+		output:       String,
 		#[arg(
 			long,
 			value_name = "PAKLIST",
-			required = true,
-			help = "Set the pakelist name"
+			required_unless_present = "from_dir",
+			help = "Set the paklist name, with names, globs, !excludes, @include other.paklist, and # comments"
 		)]
-		paklist:  String,
+		paklist:      Option<String>,
+		#[arg(
+			long,
+			help = "Pack all files below the base path, except the ones ignored by its .omtignore"
+		)]
+		from_dir:     bool,
 		#[arg(
 			long,
 			value_name = "NAME_MAP",
 			help = "Set the (optional) name map file"
 		)]
-		name_map: Option<String>,
+		name_map:     Option<String>,
+		#[arg(
+			long,
+			help = "Compress entries with the method that shrinks them most (version 3)"
+		)]
+		compress:     bool,
+		#[arg(
+			long,
+			help = "Embed the names into the archive, e.g. for debug builds, list and unpack use them"
+		)]
+		embed_names:  bool,
+		#[arg(
+			long,
+			help = "Store checksums of the data, e.g. for verify (version 4)"
+		)]
+		checksums:    bool,
+		#[arg(
+			long,
+			value_name = "NAME_HASH",
+			default_value = "crc32",
+			help = "Set the hash for names, crc32, crc32c, or fnv1a, e.g. for colliding names (version 5)"
+		)]
+		name_hash:    NameHash,
+		#[arg(
+			long,
+			value_name = "SALT",
+			default_value_t = 0,
+			help = "Set the salt for the name hash (version 5)"
+		)]
+		salt:         u32,
+		#[arg(
+			long,
+			value_name = "ALIGN",
+			help = "Align entries in the archive, e.g. 16, or per glob *.ktx=4096, can be repeated (version 6)"
+		)]
+		align:        Vec<AlignRule>,
+		#[arg(
+			long,
+			value_name = "ORDER",
+			default_value = "paklist",
+			help = "Set the order of the entries, paklist, crc, or name"
+		)]
+		sort:         EntryOrder,
+		#[arg(
+			long,
+			help = "Make the archive independent of the paklist order, and the filesystem, sorts by crc unless --sort name"
+		)]
+		reproducible: bool,
 	},
 	Unpack {
 		#[arg(
@@ -68,10 +130,139 @@ enum Commands {
 			help = "Set the (optional) name map file"
 		)]
 		name_map:   Option<String>,
-		#[arg(long, help = "Use names for file instead of symlinking")]
+		#[arg(
+			long,
+			conflicts_with = "layout",
+			help = "Use names for files, same as --layout tree"
+		)]
 		names_only: bool,
+		#[arg(
+			long,
+			value_name = "LAYOUT",
+			default_value = "crc",
+			help = "Name files by crc, by name in nested directories (tree), or by name in the target path (flat)"
+		)]
+		layout:     UnpackLayout,
+		#[arg(
+			long,
+			value_name = "GLOB",
+			help = "Only unpack entries with matching names, embedded, or from the name map, can be repeated"
+		)]
+		name:       Vec<String>,
+		#[arg(
+			long,
+			value_name = "CRC",
+			value_parser = EntryFilter::parse_crc,
+			help = "Only unpack entries with this crc, e.g. 0x1234abcd, can be repeated"
+		)]
+		crc:        Vec<u32>,
+		#[arg(
+			long,
+			value_name = "BYTES",
+			help = "Only unpack entries with at least this size"
+		)]
+		min_size:   Option<u64>,
+		#[arg(
+			long,
+			value_name = "BYTES",
+			help = "Only unpack entries with at most this size"
+		)]
+		max_size:   Option<u64>,
+	},
+	Cat {
+		#[arg(
+			long,
+			value_name = "INPUT",
+			required = true,
+			help = "Set the input filename"
+		)]
+		input: String,
+		#[arg(
+			long,
+			value_name = "NAME",
+			required_unless_present = "crc",
+			conflicts_with = "crc",
+			help = "Write the entry with this name to stdout"
+		)]
+		name:  Option<String>,
+		#[arg(
+			long,
+			value_name = "CRC",
+			value_parser = EntryFilter::parse_crc,
+			help = "Write the entry with this crc to stdout, e.g. 0x1234abcd"
+		)]
+		crc:   Option<u32>,
 	},
 	List {
+		#[arg(
+			long,
+			value_name = "INPUT",
+			required_unless_present = "overlay",
+			conflicts_with = "overlay",
+			help = "Set the input filename"
+		)]
+		input:    Option<String>,
+		#[arg(
+			long,
+			value_name = "NAME_MAP",
+			help = "Set the (optional) name map file"
+		)]
+		name_map: Option<String>,
+		#[arg(
+			long,
+			value_name = "ARCHIVE",
+			num_args = 1..,
+			help = "List the effective entries of archives mounted in priority order, later ones win"
+		)]
+		overlay:  Vec<String>,
+	},
+	Diff {
+		#[arg(
+			long,
+			value_name = "OLD",
+			required = true,
+			help = "Set the old archive"
+		)]
+		old:    String,
+		#[arg(
+			long,
+			value_name = "NEW",
+			required = true,
+			help = "Set the new archive"
+		)]
+		new:    String,
+		#[arg(
+			long,
+			value_name = "OUTPUT",
+			required = true,
+			help = "Set the output filename for the patch archive"
+		)]
+		output: String,
+	},
+	Apply {
+		#[arg(
+			long,
+			value_name = "INPUT",
+			required = true,
+			help = "Set the (old) input archive"
+		)]
+		input:  String,
+		#[arg(
+			long,
+			value_name = "PATCH",
+			required = true,
+			help = "Set the patch archive, created by diff"
+		)]
+		patch:  String,
+		#[arg(
+			long,
+			value_name = "OUTPUT",
+			required = true,
+			help = "Set the output filename for the new archive"
+		)]
+		output: String,
+	},
+	Stats {
 		#[arg(
 			long,
 			value_name = "INPUT",
@@ -85,6 +276,24 @@ enum Commands {
 			help = "Set the (optional) name map file"
 		)]
 		name_map: Option<String>,
+		#[arg(long, help = "Print the stats as json")]
+		json:     bool,
+		#[arg(
+			long,
+			value_name = "COUNT",
+			default_value_t = 10,
+			help = "Set the number of largest entries to show"
+		)]
+		largest:  usize,
+	},
+	Verify {
+		#[arg(
+			long,
+			value_name = "INPUT",
+			required = true,
+			help = "Set the input filename"
+		)]
+		input: String,
 	},
 }
 
@@ -99,15 +308,34 @@ fn main() {
 			basepath,
 			output,
 			paklist,
+			from_dir,
 			name_map,
+			compress,
+			embed_names,
+			checksums,
+			name_hash,
+			salt,
+			align,
+			sort,
+			reproducible,
 		}) => {
 			let mut command = Box::new(CommandPackerPack::new()) as Box<dyn CommandPacker>;
 			command.set_basepath(&basepath);
 			command.set_output(&output);
-			command.set_paklist(&paklist);
+			if let Some(paklist) = paklist {
+				command.set_paklist(&paklist);
+			}
+			command.set_from_dir(from_dir);
 			if let Some(name_map) = name_map {
 				command.set_name_map(&name_map);
 			}
+			command.set_compress(compress);
+			command.set_embed_names(embed_names);
+			command.set_checksums(checksums);
+			command.set_name_hash(name_hash, salt);
+			command.set_align_rules(&align);
+			command.set_entry_order(sort);
+			command.set_reproducible(reproducible);
 			Some(command)
 		},
 		Some(Commands::Unpack {
@@ -115,6 +343,11 @@ fn main() {
 			input,
 			name_map,
 			names_only,
+			layout,
+			name,
+			crc,
+			min_size,
+			max_size,
 		}) => {
 			let mut command = Box::new(CommandPackerUnpack::new()) as Box<dyn CommandPacker>;
 			command.set_targetpath(&targetpath);
@@ -122,15 +355,85 @@ fn main() {
 			if let Some(name_map) = name_map {
 				command.set_name_map(&name_map);
 			}
+			command.set_layout(layout);
 			command.set_names_only(names_only);
+			let mut filter = EntryFilter::new();
+			for name in name {
+				if let Err(e) = filter.add_name(&name) {
+					println!("{}", e);
+					process::exit(-1);
+				}
+			}
+			for crc in crc {
+				filter.add_crc(crc);
+			}
+			filter.set_min_size(min_size);
+			filter.set_max_size(max_size);
+			command.set_filter(filter);
+			Some(command)
+		},
+		Some(Commands::Cat { input, name, crc }) => {
+			let mut command = Box::new(CommandPackerCat::new()) as Box<dyn CommandPacker>;
+			command.set_input(&input);
+			if let Some(name) = name {
+				command.set_name(&name);
+			}
+			if let Some(crc) = crc {
+				command.set_crc(crc);
+			}
 			Some(command)
 		},
-		Some(Commands::List { input, name_map }) => {
+		Some(Commands::List {
+			input,
+			name_map,
+			overlay,
+		}) => {
 			let mut command = Box::new(CommandPackerList::new()) as Box<dyn CommandPacker>;
+			if let Some(input) = input {
+				command.set_input(&input);
+			}
+			command.set_overlay(&overlay);
+			if let Some(name_map) = name_map {
+				command.set_name_map(&name_map);
+			}
+			Some(command)
+		},
+		Some(Commands::Diff { old, new, output }) => {
+			let mut command = Box::new(CommandPackerDiff::new()) as Box<dyn CommandPacker>;
+			command.set_old(&old);
+			command.set_new(&new);
+			command.set_output(&output);
+			Some(command)
+		},
+		Some(Commands::Apply {
+			input,
+			patch,
+			output,
+		}) => {
+			let mut command = Box::new(CommandPackerApply::new()) as Box<dyn CommandPacker>;
+			command.set_input(&input);
+			command.set_patch(&patch);
+			command.set_output(&output);
+			Some(command)
+		},
+		Some(Commands::Stats {
+			input,
+			name_map,
+			json,
+			largest,
+		}) => {
+			let mut command = Box::new(CommandPackerStats::new()) as Box<dyn CommandPacker>;
 			command.set_input(&input);
 			if let Some(name_map) = name_map {
 				command.set_name_map(&name_map);
 			}
+			command.set_json(json);
+			command.set_largest(largest);
+			Some(command)
+		},
+		Some(Commands::Verify { input }) => {
+			let mut command = Box::new(CommandPackerVerify::new()) as Box<dyn CommandPacker>;
+			command.set_input(&input);
 			Some(command)
 		},
 		None => {
@@ -0,0 +1,78 @@
+// Alignment of entries in the archive, e.g. "16", or per glob "*.ktx=4096".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignRule {
+	pattern: Option<glob::Pattern>,
+	align:   u32,
+}
+
+impl AlignRule {
+	pub fn new(pattern: Option<&str>, align: u32) -> Result<AlignRule, String> {
+		if !align.is_power_of_two() {
+			return Err(format!("Alignment {} is not a power of two", align));
+		}
+		let pattern = match pattern.map(glob::Pattern::new).transpose() {
+			Ok(p) => p,
+			Err(e) => {
+				return Err(format!(
+					"Broken glob {}: {}",
+					pattern.unwrap_or_default(),
+					e
+				))
+			},
+		};
+		Ok(AlignRule { pattern, align })
+	}
+
+	pub fn align(&self) -> u32 {
+		self.align
+	}
+
+	// rules without a glob match every name
+	pub fn matches(&self, name: &str) -> bool {
+		match &self.pattern {
+			Some(pattern) => pattern.matches(name),
+			None => true,
+		}
+	}
+
+	// the global rule, overridden by the last matching glob
+	pub fn align_for(rules: &[AlignRule], name: &str) -> u32 {
+		let global = rules
+			.iter()
+			.rev()
+			.find(|r| r.pattern.is_none())
+			.map(|r| r.align)
+			.unwrap_or(1);
+		rules
+			.iter()
+			.rev()
+			.find(|r| r.pattern.is_some() && r.matches(name))
+			.map(|r| r.align)
+			.unwrap_or(global)
+	}
+}
+
+impl std::str::FromStr for AlignRule {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (pattern, align) = match s.rsplit_once('=') {
+			Some((pattern, align)) => (Some(pattern), align),
+			None => (None, s),
+		};
+		let align = match align.trim().parse::<u32>() {
+			Ok(a) => a,
+			Err(e) => return Err(format!("Broken alignment {}: {}", s, e)),
+		};
+		AlignRule::new(pattern, align)
+	}
+}
+
+impl core::fmt::Display for AlignRule {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		match &self.pattern {
+			Some(pattern) => write!(f, "{}={}", pattern, self.align),
+			None => write!(f, "{}", self.align),
+		}
+	}
+}
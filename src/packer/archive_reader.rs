@@ -0,0 +1,717 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::packer::Compression;
+use crate::packer::NameHash;
+use crate::util::CrcHelper;
+
+// magic, version, flags, 2 reserved, number of files
+const HEADER_SIZE: u64 = 4 + 1 + 1 + 2 + 4;
+// since v5, salt, name hash, 3 reserved (v6: log2 alignment, 2 reserved)
+const HEADER_EXTENSION_SIZE: u64 = 4 + 1 + 3;
+// names are stored after the data
+pub(crate) const FLAG_NAMES: u8 = 1;
+// patch archives, crcs of removed entries are stored after the names
+pub(crate) const FLAG_TOMBSTONES: u8 = 2;
+// patch archives, the layout of the new archive is stored after the tombstones
+pub(crate) const FLAG_TARGET: u8 = 4;
+
+// crc, pos, size
+const DIRECTORY_ENTRY_SIZE_V2: u64 = 4 + 4 + 4;
+// crc, pos, stored size, size, compression, 3 reserved
+const DIRECTORY_ENTRY_SIZE_V3: u64 = 4 + 4 + 4 + 4 + 1 + 3;
+// as v3, plus checksum of the uncompressed data
+const DIRECTORY_ENTRY_SIZE_V4: u64 = DIRECTORY_ENTRY_SIZE_V3 + 4;
+// as v4, with u64 pos, stored size, and size
+const DIRECTORY_ENTRY_SIZE_V7: u64 = 4 + 8 + 8 + 8 + 1 + 3 + 4;
+
+// How the new archive of a patch was packed, so apply can rebuild it byte for byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchTarget {
+	version:     u8,
+	compress:    bool,
+	checksums:   bool,
+	embed_names: bool,
+	entries:     Vec<(u32, u32)>, // crc, and alignment, in directory order
+}
+
+impl PatchTarget {
+	// from the archive itself, the per entry alignment is the smallest one reproducing its padding
+	pub fn from_reader(reader: &ArchiveReader) -> PatchTarget {
+		let mut entries = Vec::with_capacity(reader.len());
+		let mut end = reader.data_start;
+		for entry in reader.entries() {
+			let mut align = reader.alignment();
+			while align < 1 << 31 && end.next_multiple_of(align as u64) != entry.offset {
+				align <<= 1;
+			}
+			entries.push((entry.crc, align));
+			end = entry.offset + entry.stored_size;
+		}
+		PatchTarget {
+			version: reader.version(),
+			// entries that don't shrink are stored, but version 3 is only written when compressing
+			compress: reader.version() == 3
+				|| reader
+					.entries()
+					.iter()
+					.any(|e| e.compression != Compression::Stored),
+			checksums: reader.version() >= 4,
+			embed_names: reader.has_names(),
+			entries,
+		}
+	}
+
+	pub fn version(&self) -> u8 {
+		self.version
+	}
+	pub fn compress(&self) -> bool {
+		self.compress
+	}
+	pub fn checksums(&self) -> bool {
+		self.checksums
+	}
+	pub fn embed_names(&self) -> bool {
+		self.embed_names
+	}
+	pub fn entries(&self) -> &[(u32, u32)] {
+		&self.entries
+	}
+
+	// version, compress, checksums, embed names, count, then crc, log2 alignment, 3 reserved per entry
+	pub(crate) fn write(&self, w: &mut impl std::io::Write) -> anyhow::Result<()> {
+		w.write_all(&[
+			self.version,
+			self.compress as u8,
+			self.checksums as u8,
+			self.embed_names as u8,
+		])?;
+		w.write_u32::<LittleEndian>(self.entries.len() as u32)?;
+		for (crc, align) in &self.entries {
+			w.write_u32::<LittleEndian>(*crc)?;
+			w.write_all(&[align.trailing_zeros() as u8, 0, 0, 0])?;
+		}
+		w.write_u32::<LittleEndian>(8 + self.entries.len() as u32 * 8)?;
+		Ok(())
+	}
+
+	fn read(r: &mut impl Read, table_size: u64, filename: &Path) -> anyhow::Result<PatchTarget> {
+		let mut settings = [0u8; 4];
+		r.read_exact(&mut settings)?;
+		let count = r.read_u32::<LittleEndian>()? as u64;
+		if 8 + count * 8 != table_size {
+			anyhow::bail!("Broken target table in {}", filename.display());
+		}
+		let mut entries = Vec::with_capacity(count as usize);
+		for _e in 0..count {
+			let crc = r.read_u32::<LittleEndian>()?;
+			let mut align = [0u8; 4];
+			r.read_exact(&mut align)?;
+			if align[0] > 31 {
+				anyhow::bail!("Broken alignment 2^{} in {}", align[0], filename.display());
+			}
+			entries.push((crc, 1 << align[0]));
+		}
+		Ok(PatchTarget {
+			version: settings[0],
+			compress: settings[1] != 0,
+			checksums: settings[2] != 0,
+			embed_names: settings[3] != 0,
+			entries,
+		})
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+	crc:         u32,
+	offset:      u64, // from the start of the archive
+	stored_size: u64, // in the archive, after compression
+	size:        u64,
+	compression: Compression,
+	checksum:    Option<u32>, // since v4
+}
+
+impl ArchiveEntry {
+	pub fn crc(&self) -> u32 {
+		self.crc
+	}
+	pub fn offset(&self) -> u64 {
+		self.offset
+	}
+	// uncompressed
+	pub fn size(&self) -> u64 {
+		self.size
+	}
+	pub fn stored_size(&self) -> u64 {
+		self.stored_size
+	}
+	pub fn compression(&self) -> Compression {
+		self.compression
+	}
+	pub fn checksum(&self) -> Option<u32> {
+		self.checksum
+	}
+}
+
+impl core::fmt::Display for ArchiveEntry {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		write!(
+			f,
+			"[{:#010x}] {:#10} bytes at {:#010x}",
+			self.crc, self.size, self.offset
+		)?;
+		if self.compression != Compression::Stored {
+			write!(f, " ({}, {} bytes)", self.compression, self.stored_size)?;
+		}
+		Ok(())
+	}
+}
+
+enum Storage {
+	File(File),
+	#[cfg(feature = "mmap")]
+	Mmap(memmap2::Mmap),
+}
+
+// Reads only the directory of an archive, entries are read on demand.
+pub struct ArchiveReader {
+	version:    u8,
+	flags:      u8,
+	name_hash:  NameHash,
+	salt:       u32,
+	alignment:  u32,
+	storage:    Storage,
+	data_start: u64,
+	entries:    Vec<ArchiveEntry>,
+	by_crc:     HashMap<u32, usize>,
+	names:      HashMap<u32, String>,
+	tombstones: Option<Vec<u32>>,
+	target:     Option<PatchTarget>,
+}
+
+impl core::fmt::Debug for ArchiveReader {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		f.debug_struct("ArchiveReader")
+			.field("entries", &self.entries)
+			.finish()
+	}
+}
+
+impl ArchiveReader {
+	pub fn open(filename: impl AsRef<Path>) -> anyhow::Result<ArchiveReader> {
+		let filename = filename.as_ref();
+		let file = match File::open(filename) {
+			Ok(f) => f,
+			Err(e) => anyhow::bail!("Error opening {}: {}", filename.display(), e),
+		};
+		let mut reader = ArchiveReader {
+			version:    0,
+			flags:      0,
+			name_hash:  NameHash::default(),
+			salt:       0,
+			alignment:  1,
+			storage:    Storage::File(file),
+			data_start: 0,
+			entries:    Vec::new(),
+			by_crc:     HashMap::new(),
+			names:      HashMap::new(),
+			tombstones: None,
+			target:     None,
+		};
+		reader.read_directory(filename)?;
+		Ok(reader)
+	}
+
+	// maps the whole archive into memory, reads are plain copies
+	#[cfg(feature = "mmap")]
+	pub fn open_mmap(filename: impl AsRef<Path>) -> anyhow::Result<ArchiveReader> {
+		let filename = filename.as_ref();
+		let file = match File::open(filename) {
+			Ok(f) => f,
+			Err(e) => anyhow::bail!("Error opening {}: {}", filename.display(), e),
+		};
+		// the archive must not be modified while it is mapped
+		let mmap = match unsafe { memmap2::Mmap::map(&file) } {
+			Ok(m) => m,
+			Err(e) => anyhow::bail!("Error mapping {}: {}", filename.display(), e),
+		};
+		let mut reader = ArchiveReader {
+			version:    0,
+			flags:      0,
+			name_hash:  NameHash::default(),
+			salt:       0,
+			alignment:  1,
+			storage:    Storage::Mmap(mmap),
+			data_start: 0,
+			entries:    Vec::new(),
+			by_crc:     HashMap::new(),
+			names:      HashMap::new(),
+			tombstones: None,
+			target:     None,
+		};
+		reader.read_directory(filename)?;
+		Ok(reader)
+	}
+
+	fn archive_size(&self) -> anyhow::Result<u64> {
+		match &self.storage {
+			Storage::File(file) => Ok(file.metadata()?.len()),
+			#[cfg(feature = "mmap")]
+			Storage::Mmap(mmap) => Ok(mmap.len() as u64),
+		}
+	}
+
+	fn read_directory(&mut self, filename: &Path) -> anyhow::Result<()> {
+		let archive_size = self.archive_size()?;
+		let whole_archive = ArchiveEntry {
+			crc:         0,
+			offset:      0,
+			stored_size: archive_size,
+			size:        archive_size,
+			compression: Compression::Stored,
+			checksum:    None,
+		};
+		let mut r = BufReader::new(EntryReader::new(&self.storage, &whole_archive));
+
+		let mut magic = [0u8; 4];
+		if r.read_exact(&mut magic).is_err() || &magic != b"OMAR" {
+			anyhow::bail!("Broken magic in {}, expected OMAR", filename.display());
+		}
+		let version = r.read_u8()?;
+		if !(2..=7).contains(&version) {
+			anyhow::bail!("Unsupported version {} in {}", version, filename.display());
+		}
+		self.version = version;
+		let flags = r.read_u8()?;
+		if flags & !(FLAG_NAMES | FLAG_TOMBSTONES | FLAG_TARGET) != 0 {
+			anyhow::bail!("Unsupported flags {:#04x} in {}", flags, filename.display());
+		}
+		self.flags = flags;
+		let mut reserved = [0u8; 2];
+		r.read_exact(&mut reserved)?;
+		if reserved != [0, 0] {
+			anyhow::bail!("Reserved field not zero in {}", filename.display());
+		}
+
+		let number_of_files = r.read_u32::<LittleEndian>()? as u64;
+		let header_size = if version >= 5 {
+			self.salt = r.read_u32::<LittleEndian>()?;
+			self.name_hash = NameHash::from_u8(r.read_u8()?)?;
+			let mut reserved = [0u8; 3];
+			r.read_exact(&mut reserved)?;
+			if version >= 6 {
+				if reserved[0] > 31 {
+					anyhow::bail!(
+						"Broken alignment 2^{} in {}",
+						reserved[0],
+						filename.display()
+					);
+				}
+				self.alignment = 1 << reserved[0];
+				reserved[0] = 0;
+			}
+			if reserved != [0, 0, 0] {
+				anyhow::bail!("Reserved field not zero in {}", filename.display());
+			}
+			HEADER_SIZE + HEADER_EXTENSION_SIZE
+		} else {
+			HEADER_SIZE
+		};
+		// the tables after the data end with their size, names first, then tombstones, and the target
+		let mut data_end = archive_size;
+		let target_start = if flags & FLAG_TARGET != 0 {
+			data_end = self.table_start(data_end, header_size, "Target", filename)?;
+			Some(data_end)
+		} else {
+			None
+		};
+		let tombstones_end = data_end;
+		let tombstones_start = if flags & FLAG_TOMBSTONES != 0 {
+			data_end = self.table_start(data_end, header_size, "Tombstone", filename)?;
+			Some(data_end)
+		} else {
+			None
+		};
+		if flags & FLAG_NAMES != 0 {
+			data_end = self.table_start(data_end, header_size, "Name", filename)?;
+		}
+		let directory_entry_size = match version {
+			2 => DIRECTORY_ENTRY_SIZE_V2,
+			3 => DIRECTORY_ENTRY_SIZE_V3,
+			// v5, and v6 only extend the header
+			4..=6 => DIRECTORY_ENTRY_SIZE_V4,
+			_ => DIRECTORY_ENTRY_SIZE_V7,
+		};
+		let data_start = header_size + number_of_files * directory_entry_size;
+		self.data_start = data_start;
+		if data_start > data_end {
+			anyhow::bail!(
+				"Directory of {} files doesn't fit into {}",
+				number_of_files,
+				filename.display()
+			);
+		}
+
+		for _e in 0..number_of_files {
+			let crc = r.read_u32::<LittleEndian>()?;
+			let (pos, stored_size) = if version >= 7 {
+				(r.read_u64::<LittleEndian>()?, r.read_u64::<LittleEndian>()?)
+			} else {
+				(
+					r.read_u32::<LittleEndian>()? as u64,
+					r.read_u32::<LittleEndian>()? as u64,
+				)
+			};
+			let (size, compression) = if version >= 3 {
+				let size = if version >= 7 {
+					r.read_u64::<LittleEndian>()?
+				} else {
+					r.read_u32::<LittleEndian>()? as u64
+				};
+				let compression = Compression::from_u8(r.read_u8()?)?;
+				let mut reserved = [0u8; 3];
+				r.read_exact(&mut reserved)?;
+				(size, compression)
+			} else {
+				(stored_size, Compression::Stored)
+			};
+			let checksum = if version >= 4 {
+				Some(r.read_u32::<LittleEndian>()?)
+			} else {
+				None
+			};
+			let offset = data_start.saturating_add(pos);
+			if offset.saturating_add(stored_size) > data_end {
+				anyhow::bail!(
+					"Entry {:#010x} is outside of {}, broken archive",
+					crc,
+					filename.display()
+				);
+			}
+			// sizes are used for allocations, stored entries have one size only
+			if size > compression.max_size(stored_size)
+				|| (compression == Compression::Stored && size != stored_size)
+			{
+				anyhow::bail!(
+					"Entry {:#010x} has a broken size {} for {} stored bytes in {}",
+					crc,
+					size,
+					stored_size,
+					filename.display()
+				);
+			}
+			// the first entry wins for duplicated crcs
+			self.by_crc.entry(crc).or_insert(self.entries.len());
+			self.entries.push(ArchiveEntry {
+				crc,
+				offset,
+				stored_size,
+				size,
+				compression,
+				checksum,
+			});
+		}
+
+		if flags & FLAG_NAMES != 0 {
+			r.seek(SeekFrom::Start(data_end))?;
+			for idx in 0..self.entries.len() {
+				let crc = self.entries[idx].crc;
+				let len = r.read_u16::<LittleEndian>()? as usize;
+				let mut buf = vec![0u8; len];
+				r.read_exact(&mut buf)?;
+				let name = match String::from_utf8(buf) {
+					Ok(n) => n,
+					Err(e) => anyhow::bail!("Broken name in {}: {}", filename.display(), e),
+				};
+				if self.name_hash.crc_from_name(&name, self.salt) != crc {
+					anyhow::bail!(
+						"Name {} doesn't match entry {:#010x} in {}",
+						name,
+						crc,
+						filename.display()
+					);
+				}
+				self.names.entry(crc).or_insert(name);
+			}
+		}
+
+		if let Some(start) = tombstones_start {
+			r.seek(SeekFrom::Start(start))?;
+			let count = (tombstones_end - 4 - start) / 4;
+			let mut tombstones = Vec::with_capacity(count as usize);
+			for _t in 0..count {
+				tombstones.push(r.read_u32::<LittleEndian>()?);
+			}
+			self.tombstones = Some(tombstones);
+		}
+
+		if let Some(start) = target_start {
+			r.seek(SeekFrom::Start(start))?;
+			self.target = Some(PatchTarget::read(
+				&mut r,
+				archive_size - 4 - start,
+				filename,
+			)?);
+		}
+
+		Ok(())
+	}
+
+	// from the size stored in the last 4 bytes before end
+	fn table_start(
+		&self,
+		end: u64,
+		header_size: u64,
+		table: &str,
+		filename: &Path,
+	) -> anyhow::Result<u64> {
+		if end < header_size + 4 {
+			anyhow::bail!("{} table missing in {}", table, filename.display());
+		}
+		let whole_archive = ArchiveEntry {
+			crc:         0,
+			offset:      0,
+			stored_size: end,
+			size:        end,
+			compression: Compression::Stored,
+			checksum:    None,
+		};
+		let mut size = [0u8; 4];
+		let mut footer = EntryReader::new(&self.storage, &whole_archive);
+		footer.seek(SeekFrom::Start(end - 4))?;
+		footer.read_exact(&mut size)?;
+		match (end - 4).checked_sub(u32::from_le_bytes(size) as u64) {
+			Some(start) if start >= header_size => Ok(start),
+			_ => anyhow::bail!("{} table doesn't fit into {}", table, filename.display()),
+		}
+	}
+
+	pub fn version(&self) -> u8 {
+		self.version
+	}
+
+	pub fn entries(&self) -> &[ArchiveEntry] {
+		&self.entries
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn find_by_crc(&self, crc: u32) -> Option<&ArchiveEntry> {
+		self.by_crc.get(&crc).map(|idx| &self.entries[*idx])
+	}
+
+	// the name as given when packing, e.g. "textures/player.png"
+	pub fn find(&self, name: &str) -> Option<&ArchiveEntry> {
+		self.find_by_crc(self.crc_from_name(name))
+	}
+
+	// with the name hash, and salt of the archive
+	pub fn crc_from_name(&self, name: &str) -> u32 {
+		self.name_hash.crc_from_name(name, self.salt)
+	}
+
+	pub fn name_hash(&self) -> NameHash {
+		self.name_hash
+	}
+
+	pub fn salt(&self) -> u32 {
+		self.salt
+	}
+
+	// all entries start at a multiple of this, from the start of the archive
+	pub fn alignment(&self) -> u32 {
+		self.alignment
+	}
+
+	// only available for archives packed with embedded names
+	pub fn name(&self, entry: &ArchiveEntry) -> Option<&str> {
+		self.name_by_crc(entry.crc)
+	}
+
+	pub fn name_by_crc(&self, crc: u32) -> Option<&str> {
+		self.names.get(&crc).map(|n| n.as_str())
+	}
+
+	pub fn has_names(&self) -> bool {
+		self.flags & FLAG_NAMES != 0
+	}
+
+	// crcs of entries removed by a patch archive, None for normal archives
+	pub fn tombstones(&self) -> Option<&[u32]> {
+		self.tombstones.as_deref()
+	}
+
+	// the layout of the new archive, for patches written since the target table exists
+	pub fn target(&self) -> Option<&PatchTarget> {
+		self.target.as_ref()
+	}
+
+	pub fn read(&self, entry: &ArchiveEntry) -> anyhow::Result<Vec<u8>> {
+		// sizes are checked against the stored size when reading the directory,
+		// still only reserve a bit, well compressed entries grow the vec
+		let capacity = entry.size.min(entry.stored_size.saturating_mul(4));
+		let mut data = Vec::with_capacity(capacity as usize);
+		self.open_entry(entry).read_to_end(&mut data)?;
+		if data.len() as u64 != entry.size {
+			anyhow::bail!(
+				"Entry {:#010x} truncated, expected {} bytes, got {}",
+				entry.crc,
+				entry.size,
+				data.len()
+			);
+		}
+		Ok(data)
+	}
+
+	// reads the entry, and compares the checksum if the archive has them
+	pub fn verify(&self, entry: &ArchiveEntry) -> anyhow::Result<()> {
+		let data = self.read(entry)?;
+		if let Some(checksum) = entry.checksum {
+			let actual = CrcHelper::crc_from_data(&data);
+			if actual != checksum {
+				anyhow::bail!(
+					"Checksum mismatch for entry {:#010x}, expected {:#010x}, got {:#010x}",
+					entry.crc,
+					checksum,
+					actual
+				);
+			}
+		}
+		Ok(())
+	}
+
+	// streaming access, only the bytes read are loaded, compressed entries are decompressed on the first read
+	pub fn open_entry(&self, entry: &ArchiveEntry) -> EntryReader<'_> {
+		EntryReader::new(&self.storage, entry)
+	}
+
+	// the first limit bytes of an entry, compressed entries are only decompressed that far
+	pub fn read_entry_prefix(&self, entry: &ArchiveEntry, limit: usize) -> anyhow::Result<Vec<u8>> {
+		// a reader over the stored bytes, without decompressing
+		let stored = ArchiveEntry {
+			size: entry.stored_size,
+			compression: Compression::Stored,
+			..*entry
+		};
+		let reader = EntryReader::new(&self.storage, &stored);
+		entry
+			.compression
+			.decompress_prefix(reader, entry.size as usize, limit)
+	}
+}
+
+// Read + Seek for a single entry, positions are relative to the start of the (uncompressed) entry.
+// Reads are positional, without a shared cursor, so several readers, and threads can read at the same time.
+pub struct EntryReader<'a> {
+	storage:      &'a Storage,
+	entry:        ArchiveEntry,
+	decompressed: Option<Vec<u8>>,
+	pos:          u64,
+}
+
+impl<'a> EntryReader<'a> {
+	fn new(storage: &'a Storage, entry: &ArchiveEntry) -> EntryReader<'a> {
+		EntryReader {
+			storage,
+			entry: *entry,
+			decompressed: None,
+			pos: 0,
+		}
+	}
+
+	// from the stored bytes, pos is relative to the start of the entry
+	fn read_stored(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+		let len = (buf.len() as u64).min(self.entry.stored_size.saturating_sub(pos)) as usize;
+		if len == 0 {
+			return Ok(0);
+		}
+		let start = self.entry.offset + pos;
+		match self.storage {
+			#[cfg(unix)]
+			Storage::File(file) => std::os::unix::fs::FileExt::read_at(file, &mut buf[..len], start),
+			#[cfg(windows)]
+			Storage::File(file) => std::os::windows::fs::FileExt::seek_read(file, &mut buf[..len], start),
+			// shares the cursor, only one reader at a time
+			#[cfg(not(any(unix, windows)))]
+			Storage::File(file) => {
+				let mut file = file;
+				file.seek(SeekFrom::Start(start))?;
+				file.read(&mut buf[..len])
+			},
+			#[cfg(feature = "mmap")]
+			Storage::Mmap(mmap) => {
+				let start = start as usize;
+				buf[..len].copy_from_slice(&mmap[start..start + len]);
+				Ok(len)
+			},
+		}
+	}
+
+	fn decompress(&self) -> std::io::Result<Vec<u8>> {
+		let mut stored = vec![0u8; self.entry.stored_size as usize];
+		let mut filled = 0;
+		while filled < stored.len() {
+			match self.read_stored(filled as u64, &mut stored[filled..])? {
+				0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+				n => filled += n,
+			}
+		}
+		self.entry
+			.compression
+			.decompress(&stored, self.entry.size as usize)
+			.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+	}
+}
+
+impl Read for EntryReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		if self.pos >= self.entry.size {
+			return Ok(0);
+		}
+		let n = if self.entry.compression == Compression::Stored {
+			self.read_stored(self.pos, buf)?
+		} else {
+			if self.decompressed.is_none() {
+				self.decompressed = Some(self.decompress()?);
+			}
+			let data = self.decompressed.as_deref().unwrap_or_default();
+			let data = &data[self.pos as usize..];
+			let len = buf.len().min(data.len());
+			buf[..len].copy_from_slice(&data[..len]);
+			len
+		};
+		self.pos += n as u64;
+		Ok(n)
+	}
+}
+
+impl Seek for EntryReader<'_> {
+	fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+		let new_pos = match pos {
+			SeekFrom::Start(p) => Some(p),
+			SeekFrom::End(d) => self.entry.size.checked_add_signed(d),
+			SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+		};
+		match new_pos {
+			Some(p) => {
+				self.pos = p;
+				Ok(p)
+			},
+			None => Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				"seek before the start of the entry",
+			)),
+		}
+	}
+}
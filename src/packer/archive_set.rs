@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::packer::ArchiveEntry;
+use crate::packer::ArchiveReader;
+
+// Where an entry of an ArchiveSet comes from, indices are in mount order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayEntry {
+	crc:      u32,
+	archive:  Option<usize>, // None if removed by a tombstone, from one of the shadowed archives
+	shadowed: Vec<usize>,
+}
+
+impl OverlayEntry {
+	pub fn crc(&self) -> u32 {
+		self.crc
+	}
+	pub fn archive(&self) -> Option<usize> {
+		self.archive
+	}
+	pub fn shadowed(&self) -> &[usize] {
+		&self.shadowed
+	}
+}
+
+// Several archives mounted in priority order, e.g. base, dlc, and patches.
+// Later archives override entries of earlier ones by crc, tombstones of patch archives remove them.
+#[derive(Debug, Default)]
+pub struct ArchiveSet {
+	archives: Vec<(String, ArchiveReader)>,
+}
+
+impl ArchiveSet {
+	pub fn new() -> Self {
+		Self {
+			..Default::default()
+		}
+	}
+
+	// with a higher priority than all archives mounted before
+	pub fn mount(&mut self, filename: impl AsRef<Path>) -> anyhow::Result<()> {
+		let filename = filename.as_ref();
+		let reader = ArchiveReader::open(filename)?;
+		self.mount_reader(&filename.to_string_lossy(), reader)
+	}
+
+	pub fn mount_reader(&mut self, name: &str, reader: ArchiveReader) -> anyhow::Result<()> {
+		// crcs are only comparable with the same name hash
+		if let Some((first_name, first)) = self.archives.first() {
+			if (first.name_hash(), first.salt()) != (reader.name_hash(), reader.salt()) {
+				anyhow::bail!("Name hash, or salt of {} differ from {}", name, first_name);
+			}
+		}
+		self.archives.push((name.to_string(), reader));
+		Ok(())
+	}
+
+	pub fn len(&self) -> usize {
+		self.archives.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.archives.is_empty()
+	}
+
+	pub fn archive(&self, idx: usize) -> Option<&ArchiveReader> {
+		self.archives.get(idx).map(|(_, reader)| reader)
+	}
+
+	pub fn archive_name(&self, idx: usize) -> Option<&str> {
+		self.archives.get(idx).map(|(name, _)| name.as_str())
+	}
+
+	// the effective entry, from the archive mounted last, read it with the returned reader
+	pub fn find_by_crc(&self, crc: u32) -> Option<(&ArchiveReader, &ArchiveEntry)> {
+		for (_, reader) in self.archives.iter().rev() {
+			if let Some(entry) = reader.find_by_crc(crc) {
+				return Some((reader, entry));
+			}
+			if reader.tombstones().unwrap_or_default().contains(&crc) {
+				return None;
+			}
+		}
+		None
+	}
+
+	pub fn find(&self, name: &str) -> Option<(&ArchiveReader, &ArchiveEntry)> {
+		let (_, first) = self.archives.first()?;
+		self.find_by_crc(first.crc_from_name(name))
+	}
+
+	pub fn read_by_name(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+		match self.find(name) {
+			Some((reader, entry)) => Ok(Some(reader.read(entry)?)),
+			None => Ok(None),
+		}
+	}
+
+	// from any archive with embedded names
+	pub fn name(&self, crc: u32) -> Option<&str> {
+		self.archives
+			.iter()
+			.rev()
+			.find_map(|(_, reader)| reader.name_by_crc(crc))
+	}
+
+	// every crc in any archive, in order of first appearance,
+	// tombstones for crcs no earlier archive contains are skipped
+	pub fn overlay(&self) -> Vec<OverlayEntry> {
+		let mut overlay: Vec<OverlayEntry> = Vec::new();
+		let mut by_crc = HashMap::new();
+		for (idx, (_, reader)) in self.archives.iter().enumerate() {
+			let removed = reader
+				.tombstones()
+				.unwrap_or_default()
+				.iter()
+				.map(|crc| (*crc, None));
+			let added = reader.entries().iter().map(|e| (e.crc(), Some(idx)));
+			for (crc, archive) in removed.chain(added) {
+				if archive.is_none() && !by_crc.contains_key(&crc) {
+					continue;
+				}
+				let i = *by_crc.entry(crc).or_insert_with(|| {
+					overlay.push(OverlayEntry {
+						crc,
+						archive: None,
+						shadowed: Vec::new(),
+					});
+					overlay.len() - 1
+				});
+				let o = &mut overlay[i];
+				if let Some(previous) = o.archive {
+					// duplicated crcs in one archive, the first wins
+					if previous == idx {
+						continue;
+					}
+					o.shadowed.push(previous);
+				}
+				o.archive = archive;
+			}
+		}
+		overlay
+	}
+}
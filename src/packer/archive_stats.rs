@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::name_map::NameMap;
+use crate::packer::ArchiveReader;
+
+// enough for all magics, and to tell text from binary
+const SNIFF_SIZE: usize = 512;
+
+// The type of an entry, from its magic bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryType {
+	Atlas,
+	Font,
+	Soundbank,
+	NameMap,
+	Archive,
+	Png,
+	Ogg,
+	Wav,
+	Lua,
+	Text,
+	Unknown,
+}
+
+impl EntryType {
+	// Lua sources have no magic, they are only recognised by name, otherwise they are text
+	pub fn sniff(data: &[u8], name: Option<&str>) -> EntryType {
+		// atlases, and soundbanks start with "SO", and a u16 version before the chunk magic
+		let chunk = data.get(4..).unwrap_or_default();
+		if chunk.starts_with(b"OMATLAS") {
+			EntryType::Atlas
+		} else if chunk.starts_with(b"OMSNDBN") {
+			EntryType::Soundbank
+		} else if data.starts_with(b"OMFONT") || data.starts_with(b"OMFN") {
+			EntryType::Font
+		} else if data.starts_with(b"OMNA") {
+			EntryType::NameMap
+		} else if data.starts_with(b"OMAR") {
+			EntryType::Archive
+		} else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+			EntryType::Png
+		} else if data.starts_with(b"OggS") {
+			EntryType::Ogg
+		} else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WAVE") {
+			EntryType::Wav
+		} else if data.starts_with(b"\x1bLua") {
+			EntryType::Lua
+		} else if Self::is_text(data) {
+			match name {
+				Some(name) if name.to_lowercase().ends_with(".lua") => EntryType::Lua,
+				_ => EntryType::Text,
+			}
+		} else {
+			EntryType::Unknown
+		}
+	}
+
+	// utf-8, possibly cut in the middle of a character, without nul bytes
+	fn is_text(data: &[u8]) -> bool {
+		if data.is_empty() || data.contains(&0) {
+			return false;
+		}
+		match std::str::from_utf8(data) {
+			Ok(_) => true,
+			Err(e) => e.error_len().is_none(),
+		}
+	}
+}
+
+impl core::fmt::Display for EntryType {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		let name = match self {
+			EntryType::Atlas => "omatlas",
+			EntryType::Font => "omfont",
+			EntryType::Soundbank => "omsndbn",
+			EntryType::NameMap => "omna",
+			EntryType::Archive => "omar",
+			EntryType::Png => "png",
+			EntryType::Ogg => "ogg",
+			EntryType::Wav => "wav",
+			EntryType::Lua => "lua",
+			EntryType::Text => "text",
+			EntryType::Unknown => "unknown",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryStats {
+	pub crc:         u32,
+	pub name:        Option<String>,
+	pub entry_type:  EntryType,
+	pub size:        u64,
+	pub stored_size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GroupStats {
+	pub name:        String,
+	pub entries:     usize,
+	pub size:        u64,
+	pub stored_size: u64,
+}
+
+// Sizes per type, and per directory, largest first, e.g. to find what bloats an archive.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArchiveStats {
+	pub entries:     usize,
+	pub size:        u64,
+	pub stored_size: u64,
+	pub types:       Vec<GroupStats>,
+	pub directories: Vec<GroupStats>,
+	pub largest:     Vec<EntryStats>,
+}
+
+impl ArchiveStats {
+	// only the first bytes of each entry are read, and decompressed, names are embedded, or from the name map
+	pub fn collect(
+		reader: &ArchiveReader,
+		name_map: Option<&NameMap>,
+		largest: usize,
+	) -> anyhow::Result<ArchiveStats> {
+		let mut stats = ArchiveStats::default();
+		let mut types = HashMap::new();
+		let mut directories = HashMap::new();
+		let mut entries = Vec::new();
+		for entry in reader.entries() {
+			let name = reader
+				.name(entry)
+				.map(|n| n.to_string())
+				.or_else(|| name_map.and_then(|nm| nm.get_name(entry.crc()).cloned()));
+			let data = reader.read_entry_prefix(entry, SNIFF_SIZE)?;
+			let entry_type = EntryType::sniff(&data, name.as_deref());
+
+			let directory = match name.as_deref().map(|n| n.rsplit_once('/')) {
+				Some(Some((directory, _))) => directory.to_string(),
+				Some(None) => ".".to_string(),
+				None => "(unnamed)".to_string(),
+			};
+			for (groups, key) in [
+				(&mut types, entry_type.to_string()),
+				(&mut directories, directory),
+			] {
+				let group: &mut GroupStats =
+					groups.entry(key.clone()).or_insert_with(|| GroupStats {
+						name: key,
+						..Default::default()
+					});
+				group.entries += 1;
+				group.size += entry.size();
+				group.stored_size += entry.stored_size();
+			}
+
+			stats.entries += 1;
+			stats.size += entry.size();
+			stats.stored_size += entry.stored_size();
+			entries.push(EntryStats {
+				crc: entry.crc(),
+				name,
+				entry_type,
+				size: entry.size(),
+				stored_size: entry.stored_size(),
+			});
+		}
+
+		stats.types = Self::sorted(types);
+		stats.directories = Self::sorted(directories);
+		entries.sort_by(|a, b| b.size.cmp(&a.size).then(a.crc.cmp(&b.crc)));
+		entries.truncate(largest);
+		stats.largest = entries;
+		Ok(stats)
+	}
+
+	// largest first, by name for equal sizes
+	fn sorted(groups: HashMap<String, GroupStats>) -> Vec<GroupStats> {
+		let mut groups: Vec<GroupStats> = groups.into_values().collect();
+		groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+		groups
+	}
+}
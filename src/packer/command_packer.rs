@@ -1,3 +1,9 @@
+use crate::packer::AlignRule;
+use crate::packer::EntryFilter;
+use crate::packer::EntryOrder;
+use crate::packer::NameHash;
+use crate::packer::UnpackLayout;
+
 pub trait CommandPacker {
 	fn run(&mut self) -> anyhow::Result<u32> {
 		Ok(0)
@@ -5,9 +11,27 @@ pub trait CommandPacker {
 
 	fn set_basepath(&mut self, _basepath: &str) {}
 	fn set_paklist(&mut self, _paklist: &str) {}
+	fn set_from_dir(&mut self, _from_dir: bool) {}
 	fn set_output(&mut self, _output: &str) {}
 	fn set_input(&mut self, _input: &str) {}
 	fn set_name_map(&mut self, _name_map: &str) {}
 	fn set_targetpath(&mut self, _targetpath: &str) {}
 	fn set_names_only(&mut self, _names_only: bool) {}
+	fn set_layout(&mut self, _layout: UnpackLayout) {}
+	fn set_filter(&mut self, _filter: EntryFilter) {}
+	fn set_name(&mut self, _name: &str) {}
+	fn set_crc(&mut self, _crc: u32) {}
+	fn set_json(&mut self, _json: bool) {}
+	fn set_largest(&mut self, _largest: usize) {}
+	fn set_compress(&mut self, _compress: bool) {}
+	fn set_embed_names(&mut self, _embed_names: bool) {}
+	fn set_checksums(&mut self, _checksums: bool) {}
+	fn set_name_hash(&mut self, _name_hash: NameHash, _salt: u32) {}
+	fn set_align_rules(&mut self, _align_rules: &[AlignRule]) {}
+	fn set_entry_order(&mut self, _entry_order: EntryOrder) {}
+	fn set_reproducible(&mut self, _reproducible: bool) {}
+	fn set_old(&mut self, _old: &str) {}
+	fn set_new(&mut self, _new: &str) {}
+	fn set_patch(&mut self, _patch: &str) {}
+	fn set_overlay(&mut self, _overlay: &[String]) {}
 }
@@ -0,0 +1,118 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::packer::command_packer::CommandPacker;
+use crate::packer::packer::Archive;
+use crate::packer::ArchiveReader;
+
+// Rebuilds the new archive from the old one, and a patch created by diff.
+#[derive(Debug, Default)]
+pub struct CommandPackerApply {
+	input:  Option<String>,
+	patch:  Option<String>,
+	output: Option<String>,
+}
+
+impl CommandPackerApply {
+	pub fn new() -> Self {
+		Self {
+			..Default::default()
+		}
+	}
+}
+
+impl CommandPacker for CommandPackerApply {
+	fn run(&mut self) -> anyhow::Result<u32> {
+		let (input_name, patch_name, output) = match (&self.input, &self.patch, &self.output) {
+			(Some(input), Some(patch), Some(output)) => (input, patch, output),
+			_ => anyhow::bail!("Input, patch, and output are needed!"),
+		};
+		// the entries are streamed from input, and patch while writing
+		for name in [input_name, patch_name] {
+			if Path::new(output).exists()
+				&& Path::new(output).canonicalize()? == Path::new(name).canonicalize()?
+			{
+				anyhow::bail!("Output {} would overwrite {}", output, name);
+			}
+		}
+
+		let input = Rc::new(ArchiveReader::open(input_name)?);
+		let patch = Rc::new(ArchiveReader::open(patch_name)?);
+		let tombstones = match patch.tombstones() {
+			Some(t) => t,
+			None => anyhow::bail!("{} is not a patch archive", patch_name),
+		};
+		if (input.name_hash(), input.salt()) != (patch.name_hash(), patch.salt()) {
+			anyhow::bail!(
+				"Name hash, or salt differ between {} and {}",
+				input_name,
+				patch_name
+			);
+		}
+		for crc in tombstones {
+			if input.find_by_crc(*crc).is_none() {
+				anyhow::bail!(
+					"Patch {} doesn't match {}, removed entry {:#010x} is missing",
+					patch_name,
+					input_name,
+					crc
+				);
+			}
+		}
+
+		let mut archive = Archive::create("");
+		archive.configure_like(&patch);
+
+		match patch.target() {
+			// the order, alignment, and settings of the new archive
+			Some(target) => {
+				archive.configure_like_target(target);
+				for (crc, align) in target.entries() {
+					let (reader, entry) = match (patch.find_by_crc(*crc), input.find_by_crc(*crc)) {
+						(Some(entry), _) => (&patch, entry),
+						(None, Some(entry)) if !tombstones.contains(crc) => (&input, entry),
+						_ => anyhow::bail!(
+							"Patch {} doesn't match {}, entry {:#010x} is missing",
+							patch_name,
+							input_name,
+							crc
+						),
+					};
+					archive.add_entry_from_reader(reader, entry, Some(*align))?;
+				}
+			},
+			// older patches, in the order of input, changed entries replace the old ones, added entries go last
+			None => {
+				for entry in input.entries() {
+					if tombstones.contains(&entry.crc()) {
+						continue;
+					}
+					match patch.find_by_crc(entry.crc()) {
+						Some(patched) => archive.add_entry_from_reader(&patch, patched, None)?,
+						None => archive.add_entry_from_reader(&input, entry, None)?,
+					}
+				}
+				for entry in patch.entries() {
+					if input.find_by_crc(entry.crc()).is_none() {
+						archive.add_entry_from_reader(&patch, entry, None)?;
+					}
+				}
+			},
+		}
+		if !input.has_names() && patch.has_names() {
+			println!("Warning: {} has no names, not embedding them", input_name);
+			archive.set_embed_names(false);
+		}
+
+		archive.save(output)
+	}
+	fn set_input(&mut self, input: &str) {
+		self.input = Some(input.to_string());
+	}
+	fn set_patch(&mut self, patch: &str) {
+		self.patch = Some(patch.to_string());
+	}
+	fn set_output(&mut self, output: &str) {
+		self.output = Some(output.to_string());
+	}
+}
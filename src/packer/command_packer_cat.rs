@@ -0,0 +1,66 @@
+use std::io::Write;
+
+use crate::packer::command_packer::CommandPacker;
+use crate::packer::ArchiveReader;
+
+// Writes a single entry to stdout, e.g. to debug a file from a shipped archive.
+#[derive(Debug, Default)]
+pub struct CommandPackerCat {
+	input: Option<String>,
+	name:  Option<String>,
+	crc:   Option<u32>,
+}
+
+impl CommandPackerCat {
+	pub fn new() -> Self {
+		Self {
+			..Default::default()
+		}
+	}
+
+	// returns the number of bytes written
+	pub fn cat(&self, output: &mut dyn Write) -> anyhow::Result<u64> {
+		let input = match &self.input {
+			Some(input) => input,
+			None => anyhow::bail!("No input given!"),
+		};
+		let reader = ArchiveReader::open(input)?;
+		// names are hashed like the archive does, so they work without embedded names
+		let crc = match (&self.name, self.crc) {
+			(Some(name), _) => reader.crc_from_name(name),
+			(None, Some(crc)) => crc,
+			(None, None) => anyhow::bail!("No name, or crc given!"),
+		};
+		let entry = match reader.find_by_crc(crc) {
+			Some(entry) => entry,
+			None => anyhow::bail!(
+				"{} not found in {}",
+				self.name
+					.clone()
+					.unwrap_or_else(|| format!("{:#010x}", crc)),
+				input
+			),
+		};
+		let mut entry_reader = reader.open_entry(entry);
+		let size = std::io::copy(&mut entry_reader, output)?;
+		output.flush()?;
+		Ok(size)
+	}
+}
+
+impl CommandPacker for CommandPackerCat {
+	fn run(&mut self) -> anyhow::Result<u32> {
+		let stdout = std::io::stdout();
+		self.cat(&mut stdout.lock())?;
+		Ok(1)
+	}
+	fn set_input(&mut self, input: &str) {
+		self.input = Some(input.to_string());
+	}
+	fn set_name(&mut self, name: &str) {
+		self.name = Some(name.to_string());
+	}
+	fn set_crc(&mut self, crc: u32) {
+		self.crc = Some(crc);
+	}
+}
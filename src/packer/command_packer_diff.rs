@@ -0,0 +1,98 @@
+use std::rc::Rc;
+
+use crate::packer::command_packer::CommandPacker;
+use crate::packer::packer::Archive;
+use crate::packer::ArchiveEntry;
+use crate::packer::ArchiveReader;
+use crate::packer::PatchTarget;
+
+// A patch archive with the added, and changed entries of new, plus tombstones for the removed ones.
+#[derive(Debug, Default)]
+pub struct CommandPackerDiff {
+	old:    Option<String>,
+	new:    Option<String>,
+	output: Option<String>,
+}
+
+impl CommandPackerDiff {
+	pub fn new() -> Self {
+		Self {
+			..Default::default()
+		}
+	}
+
+	// checksums, if both have them, otherwise the data, embedded names must match too
+	fn same_content(
+		old: &ArchiveReader,
+		old_entry: &ArchiveEntry,
+		new: &ArchiveReader,
+		new_entry: &ArchiveEntry,
+	) -> anyhow::Result<bool> {
+		if old_entry.size() != new_entry.size()
+			|| (old.has_names() && old.name(old_entry) != new.name(new_entry))
+		{
+			return Ok(false);
+		}
+		if let (Some(a), Some(b)) = (old_entry.checksum(), new_entry.checksum()) {
+			return Ok(a == b);
+		}
+		Ok(old.read(old_entry)? == new.read(new_entry)?)
+	}
+}
+
+impl CommandPacker for CommandPackerDiff {
+	fn run(&mut self) -> anyhow::Result<u32> {
+		let (old_name, new_name, output) = match (&self.old, &self.new, &self.output) {
+			(Some(old), Some(new), Some(output)) => (old, new, output),
+			_ => anyhow::bail!("Old, new, and output are needed!"),
+		};
+		let old = ArchiveReader::open(old_name)?;
+		let new = Rc::new(ArchiveReader::open(new_name)?);
+		if (old.name_hash(), old.salt()) != (new.name_hash(), new.salt()) {
+			anyhow::bail!(
+				"Name hash, or salt differ between {} and {}, crcs can't be compared",
+				old_name,
+				new_name
+			);
+		}
+
+		let mut archive = Archive::create("");
+		archive.configure_like(&new);
+
+		for entry in new.entries() {
+			match old.find_by_crc(entry.crc()) {
+				Some(old_entry) => {
+					if Self::same_content(&old, old_entry, &new, entry)? {
+						continue;
+					}
+					println!("Changed {}", entry);
+				},
+				None => println!("Added {}", entry),
+			}
+			archive.add_entry_from_reader(&new, entry, None)?;
+		}
+
+		let tombstones: Vec<u32> = old
+			.entries()
+			.iter()
+			.filter(|e| new.find_by_crc(e.crc()).is_none())
+			.map(|e| e.crc())
+			.collect();
+		for crc in &tombstones {
+			println!("Removed [{:#010x}]", crc);
+		}
+		archive.set_tombstones(tombstones);
+		archive.set_target(PatchTarget::from_reader(&new));
+
+		archive.save(output)
+	}
+	fn set_old(&mut self, old: &str) {
+		self.old = Some(old.to_string());
+	}
+	fn set_new(&mut self, new: &str) {
+		self.new = Some(new.to_string());
+	}
+	fn set_output(&mut self, output: &str) {
+		self.output = Some(output.to_string());
+	}
+}
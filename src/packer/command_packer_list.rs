@@ -3,11 +3,13 @@ use std::fs;
 use crate::name_map::NameMap;
 use crate::packer::command_packer::CommandPacker;
 use crate::packer::packer::Archive;
+use crate::packer::ArchiveSet;
 
 #[derive(Debug, Default)]
 pub struct CommandPackerList {
 	input:    Option<String>,
 	name_map: Option<String>,
+	overlay:  Vec<String>,
 }
 
 impl CommandPackerList {
@@ -18,6 +20,45 @@ impl CommandPackerList {
 	}
 }
 
+impl CommandPackerList {
+	fn list_overlay(&self, name_map: Option<&NameMap>) -> anyhow::Result<u32> {
+		let mut set = ArchiveSet::new();
+		for filename in &self.overlay {
+			set.mount(filename)?;
+		}
+
+		let archive_name = |idx: usize| set.archive_name(idx).unwrap_or_default();
+		let mut count = 0;
+		for o in set.overlay() {
+			let name = set
+				.name(o.crc())
+				.map(|n| n.to_string())
+				.or_else(|| name_map.and_then(|nm| nm.get_name(o.crc()).cloned()))
+				.unwrap_or_default();
+			let source = match o.archive() {
+				Some(idx) => {
+					count += 1;
+					format!("from {}", archive_name(idx))
+				},
+				None => "removed".to_string(),
+			};
+			let shadowed: Vec<&str> = o.shadowed().iter().map(|idx| archive_name(*idx)).collect();
+			if shadowed.is_empty() {
+				println!("{:#010x} {} {}", o.crc(), name, source);
+			} else {
+				println!(
+					"{:#010x} {} {}, shadows {}",
+					o.crc(),
+					name,
+					source,
+					shadowed.join(", ")
+				);
+			}
+		}
+		Ok(count)
+	}
+}
+
 impl CommandPacker for CommandPackerList {
 	fn run(&mut self) -> anyhow::Result<u32> {
 		let name_map = if let Some(name_map_file) = &self.name_map {
@@ -27,6 +68,9 @@ impl CommandPacker for CommandPackerList {
 		} else {
 			None
 		};
+		if !self.overlay.is_empty() {
+			return self.list_overlay(name_map.as_ref());
+		}
 		if let Some(input) = &self.input {
 			let metadata = match fs::metadata(input) {
 				Err(_err) => anyhow::bail!("Input not found"),
@@ -63,4 +107,7 @@ impl CommandPacker for CommandPackerList {
 	fn set_name_map(&mut self, name_map: &str) {
 		self.name_map = Some(name_map.to_string());
 	}
+	fn set_overlay(&mut self, overlay: &[String]) {
+		self.overlay = overlay.to_vec();
+	}
 }
@@ -1,15 +1,30 @@
+use std::fs;
+use std::path::Path;
+
 use crate::name_map::NameMap;
 use crate::packer::command_packer::CommandPacker;
 use crate::packer::packer::Archive;
-use crate::packer::packer::Helper;
+use crate::packer::AlignRule;
+use crate::packer::EntryOrder;
+use crate::packer::NameHash;
+use crate::packer::Paklist;
 
 #[derive(Debug, Default)]
 pub struct CommandPackerPack {
-	output:     Option<String>,
-	basepath:   Option<String>,
-	paklist:    Option<String>,
-	name_map:   Option<String>,
-	names_only: bool,
+	output:       Option<String>,
+	basepath:     Option<String>,
+	paklist:      Option<String>,
+	from_dir:     bool,
+	name_map:     Option<String>,
+	names_only:   bool,
+	compress:     bool,
+	embed_names:  bool,
+	checksums:    bool,
+	name_hash:    NameHash,
+	salt:         u32,
+	align_rules:  Vec<AlignRule>,
+	entry_order:  EntryOrder,
+	reproducible: bool,
 }
 
 impl CommandPackerPack {
@@ -35,12 +50,46 @@ impl CommandPacker for CommandPackerPack {
 		};
 		let mut archive = Archive::create(basepath);
 		archive.give_name_map(name_map);
+		archive.set_compress(self.compress);
+		archive.set_embed_names(self.embed_names);
+		archive.set_checksums(self.checksums);
+		archive.set_name_hash(self.name_hash, self.salt);
+		archive.set_align_rules(&self.align_rules);
+
+		let mut paklist = Paklist::new(basepath);
+		if self.from_dir {
+			paklist.add_dir()?;
+		}
+		if let Some(paklist_file) = &self.paklist {
+			paklist.load(paklist_file)?;
+		}
+		let source = match &self.paklist {
+			Some(paklist_file) => paklist_file.as_str(),
+			None => basepath,
+		};
+		let mut filenames = paklist.into_filenames();
+		// don't pack the previous output into the new one
+		if let Some(output) = self.output.as_ref().and_then(|o| fs::canonicalize(o).ok()) {
+			filenames.retain(|f| {
+				fs::canonicalize(Path::new(basepath).join(f)).ok().as_ref() != Some(&output)
+			});
+		}
 
-		if let Some(paklist) = &self.paklist {
-			for filename in Helper::filenames_in_file(&paklist).unwrap_or(Vec::new()) {
+		if self.from_dir || self.paklist.is_some() {
+			// report all missing files, and collisions at once
+			let mut problems = Vec::new();
+			for filename in filenames {
 				// :TODO: add better error handling
 				println!("{:?}", filename);
-				archive.add_entry(&filename);
+				if let Err(e) = archive.add_entry(&filename) {
+					problems.push(e);
+				}
+			}
+			for problem in &problems {
+				println!("{}", problem);
+			}
+			if !problems.is_empty() {
+				anyhow::bail!("{} problem(s) in {}", problems.len(), source);
 			}
 		}
 
@@ -58,6 +107,12 @@ impl CommandPacker for CommandPackerPack {
 		}
 
 		let _name_map = archive.take_name_map();
+		// reproducible archives don't depend on the paklist order, unless sorted by name anyway
+		if self.reproducible && self.entry_order == EntryOrder::Paklist {
+			archive.sort_entries(EntryOrder::Crc);
+		} else {
+			archive.sort_entries(self.entry_order);
+		}
 		if let Some(output) = &self.output {
 			return archive.save(&output);
 		} else {
@@ -71,6 +126,9 @@ impl CommandPacker for CommandPackerPack {
 	fn set_paklist(&mut self, paklist: &str) {
 		self.paklist = Some(paklist.to_string());
 	}
+	fn set_from_dir(&mut self, from_dir: bool) {
+		self.from_dir = from_dir;
+	}
 	fn set_output(&mut self, output: &str) {
 		self.output = Some(output.to_string());
 	}
@@ -80,4 +138,26 @@ impl CommandPacker for CommandPackerPack {
 	fn set_names_only(&mut self, names_only: bool) {
 		self.names_only = names_only;
 	}
+	fn set_compress(&mut self, compress: bool) {
+		self.compress = compress;
+	}
+	fn set_embed_names(&mut self, embed_names: bool) {
+		self.embed_names = embed_names;
+	}
+	fn set_checksums(&mut self, checksums: bool) {
+		self.checksums = checksums;
+	}
+	fn set_name_hash(&mut self, name_hash: NameHash, salt: u32) {
+		self.name_hash = name_hash;
+		self.salt = salt;
+	}
+	fn set_align_rules(&mut self, align_rules: &[AlignRule]) {
+		self.align_rules = align_rules.to_vec();
+	}
+	fn set_entry_order(&mut self, entry_order: EntryOrder) {
+		self.entry_order = entry_order;
+	}
+	fn set_reproducible(&mut self, reproducible: bool) {
+		self.reproducible = reproducible;
+	}
 }
@@ -0,0 +1,91 @@
+use crate::name_map::NameMap;
+use crate::packer::command_packer::CommandPacker;
+use crate::packer::ArchiveReader;
+use crate::packer::ArchiveStats;
+use crate::packer::GroupStats;
+
+#[derive(Debug, Default)]
+pub struct CommandPackerStats {
+	input:    Option<String>,
+	name_map: Option<String>,
+	json:     bool,
+	largest:  usize,
+}
+
+impl CommandPackerStats {
+	pub fn new() -> Self {
+		Self {
+			largest: 10,
+			..Default::default()
+		}
+	}
+
+	fn print_groups(title: &str, groups: &[GroupStats]) {
+		println!("{}:", title);
+		for g in groups {
+			println!(
+				"\t{:<24} {:>6} entries {:>12} bytes {:>12} stored",
+				g.name, g.entries, g.size, g.stored_size
+			);
+		}
+	}
+}
+
+impl CommandPacker for CommandPackerStats {
+	fn run(&mut self) -> anyhow::Result<u32> {
+		let input = match &self.input {
+			Some(input) => input,
+			None => anyhow::bail!("No input given!"),
+		};
+		// stdout is only for the json
+		let name_map = match &self.name_map {
+			Some(name_map_file) if self.json => Some(NameMap::load_or_create(name_map_file)?),
+			Some(name_map_file) => {
+				let nm = NameMap::load_or_create(name_map_file)?;
+				println!("Loaded NameMap:\n{}", nm);
+				Some(nm)
+			},
+			None => None,
+		};
+		let reader = ArchiveReader::open(input)?;
+		let stats = ArchiveStats::collect(&reader, name_map.as_ref(), self.largest)?;
+
+		if self.json {
+			match serde_json::to_string_pretty(&stats) {
+				Ok(json) => println!("{}", json),
+				Err(e) => anyhow::bail!("Error converting stats to json: {}", e),
+			}
+		} else {
+			println!(
+				"{}: {} entries, {} bytes, {} stored",
+				input, stats.entries, stats.size, stats.stored_size
+			);
+			Self::print_groups("Types", &stats.types);
+			Self::print_groups("Directories", &stats.directories);
+			println!("Largest:");
+			for e in &stats.largest {
+				println!(
+					"\t[{:#010x}] {:>12} bytes {:>12} stored {:<8} {}",
+					e.crc,
+					e.size,
+					e.stored_size,
+					e.entry_type.to_string(),
+					e.name.as_deref().unwrap_or_default()
+				);
+			}
+		}
+		Ok(stats.entries as u32)
+	}
+	fn set_input(&mut self, input: &str) {
+		self.input = Some(input.to_string());
+	}
+	fn set_name_map(&mut self, name_map: &str) {
+		self.name_map = Some(name_map.to_string());
+	}
+	fn set_json(&mut self, json: bool) {
+		self.json = json;
+	}
+	fn set_largest(&mut self, largest: usize) {
+		self.largest = largest;
+	}
+}
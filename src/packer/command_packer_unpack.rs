@@ -3,13 +3,16 @@ use std::fs;
 use crate::name_map::NameMap;
 use crate::packer::command_packer::CommandPacker;
 use crate::packer::packer::Archive;
+use crate::packer::EntryFilter;
+use crate::packer::UnpackLayout;
 
 #[derive(Debug, Default)]
 pub struct CommandPackerUnpack {
 	input:      Option<String>,
 	targetpath: Option<String>,
 	name_map:   Option<String>,
-	names_only: bool,
+	layout:     UnpackLayout,
+	filter:     EntryFilter,
 }
 
 impl CommandPackerUnpack {
@@ -31,6 +34,7 @@ impl CommandPacker for CommandPackerUnpack {
 		};
 		let mut archive = Archive::create(&String::new());
 		archive.give_name_map(name_map);
+		archive.set_filter(self.filter.clone());
 
 		if let Some(input) = &self.input {
 			let metadata = match fs::metadata(input) {
@@ -64,9 +68,15 @@ impl CommandPacker for CommandPackerUnpack {
 			if !metadata.is_dir() {
 				anyhow::bail!("Targetpath is not a directory");
 			}
-			archive.set_names_only(self.names_only);
+			archive.set_layout(self.layout);
 
-			archive.unpack(targetpath).unwrap();
+			let skipped = archive.unpack(targetpath)?;
+			for s in &skipped {
+				println!("Skipped {}", s);
+			}
+			if !skipped.is_empty() {
+				anyhow::bail!("{} entries skipped", skipped.len());
+			}
 		}
 
 		let _name_map = archive.take_name_map();
@@ -81,7 +91,16 @@ impl CommandPacker for CommandPackerUnpack {
 	fn set_targetpath(&mut self, targetpath: &str) {
 		self.targetpath = Some(targetpath.to_string());
 	}
+	// the old name for the tree layout
 	fn set_names_only(&mut self, names_only: bool) {
-		self.names_only = names_only;
+		if names_only {
+			self.layout = UnpackLayout::Tree;
+		}
+	}
+	fn set_layout(&mut self, layout: UnpackLayout) {
+		self.layout = layout;
+	}
+	fn set_filter(&mut self, filter: EntryFilter) {
+		self.filter = filter;
 	}
 }
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::packer::command_packer::CommandPacker;
+use crate::packer::ArchiveReader;
+
+#[derive(Debug, Default)]
+pub struct CommandPackerVerify {
+	input: Option<String>,
+}
+
+impl CommandPackerVerify {
+	pub fn new() -> Self {
+		Self {
+			..Default::default()
+		}
+	}
+}
+
+impl CommandPacker for CommandPackerVerify {
+	fn run(&mut self) -> anyhow::Result<u32> {
+		let input = match &self.input {
+			Some(input) => input,
+			None => anyhow::bail!("No input given!"),
+		};
+		// header sanity, and out of range entries
+		let reader = ArchiveReader::open(input)?;
+		println!(
+			"Verifying {} entries in {} (version {})",
+			reader.len(),
+			input,
+			reader.version()
+		);
+
+		let mut problems = Vec::new();
+
+		let mut seen = HashMap::new();
+		for entry in reader.entries() {
+			if let Some(first) = seen.insert(entry.crc(), entry) {
+				problems.push(format!(
+					"Duplicate crc {:#010x} at {:#010x} and {:#010x}",
+					entry.crc(),
+					first.offset(),
+					entry.offset()
+				));
+			}
+		}
+
+		let mut by_offset: Vec<_> = reader
+			.entries()
+			.iter()
+			.filter(|e| e.stored_size() > 0)
+			.collect();
+		by_offset.sort_by_key(|e| e.offset());
+		// compare with the furthest reaching entry so far, a long entry can overlap several
+		let mut furthest: Option<(u64, u32)> = None;
+		for entry in by_offset {
+			let end = entry.offset() + entry.stored_size();
+			if let Some((furthest_end, furthest_crc)) = furthest {
+				if furthest_end > entry.offset() {
+					problems.push(format!(
+						"Entry {:#010x} overlaps entry {:#010x}",
+						furthest_crc,
+						entry.crc()
+					));
+				}
+				if furthest_end >= end {
+					continue;
+				}
+			}
+			furthest = Some((end, entry.crc()));
+		}
+
+		for entry in reader.entries() {
+			if entry.offset() % reader.alignment() as u64 != 0 {
+				problems.push(format!(
+					"Entry {:#010x} at {:#010x} is not aligned to {}",
+					entry.crc(),
+					entry.offset(),
+					reader.alignment()
+				));
+			}
+		}
+
+		if reader.version() < 4 {
+			println!(
+				"No checksums in version {}, only checking sizes",
+				reader.version()
+			);
+		}
+		for entry in reader.entries() {
+			if let Err(e) = reader.verify(entry) {
+				problems.push(e.to_string());
+			}
+		}
+
+		for problem in &problems {
+			println!("{}", problem);
+		}
+		if !problems.is_empty() {
+			anyhow::bail!("{} problem(s) found in {}", problems.len(), input);
+		}
+		println!("OK");
+		Ok(reader.len() as u32)
+	}
+	fn set_input(&mut self, input: &str) {
+		self.input = Some(input.to_string());
+	}
+}
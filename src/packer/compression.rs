@@ -0,0 +1,186 @@
+use std::io::{BufReader, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Compression {
+	#[default]
+	Stored,
+	Deflate,
+	Lz4,
+}
+
+impl Compression {
+	pub fn from_u8(v: u8) -> anyhow::Result<Compression> {
+		match v {
+			0 => Ok(Compression::Stored),
+			1 => Ok(Compression::Deflate),
+			2 => Ok(Compression::Lz4),
+			v => anyhow::bail!("Unknown compression method {}", v),
+		}
+	}
+
+	pub fn as_u8(&self) -> u8 {
+		match self {
+			Compression::Stored => 0,
+			Compression::Deflate => 1,
+			Compression::Lz4 => 2,
+		}
+	}
+
+	pub fn compress(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+		match self {
+			Compression::Stored => Ok(data.to_vec()),
+			Compression::Deflate => {
+				let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+				encoder.write_all(data)?;
+				Ok(encoder.finish()?)
+			},
+			Compression::Lz4 => Ok(lz4_flex::block::compress(data)),
+		}
+	}
+
+	// the largest uncompressed size stored size bytes can decompress to,
+	// deflate at most 1032:1, lz4 about 255:1, plus some slack for tiny entries
+	pub fn max_size(&self, stored_size: u64) -> u64 {
+		let ratio = match self {
+			Compression::Stored => return stored_size,
+			Compression::Deflate => 1032,
+			Compression::Lz4 => 255,
+		};
+		stored_size.saturating_mul(ratio).saturating_add(64)
+	}
+
+	// size is the uncompressed size
+	pub fn decompress(&self, data: &[u8], size: usize) -> anyhow::Result<Vec<u8>> {
+		// never trust the size for allocations
+		if size as u64 > self.max_size(data.len() as u64) {
+			anyhow::bail!("Broken size {} for {} bytes of {}", size, data.len(), self);
+		}
+		let decompressed = match self {
+			Compression::Stored => data.to_vec(),
+			Compression::Deflate => {
+				let mut decompressed = Vec::with_capacity(size);
+				DeflateDecoder::new(data)
+					.take(size as u64 + 1)
+					.read_to_end(&mut decompressed)?;
+				decompressed
+			},
+			Compression::Lz4 => match lz4_flex::block::decompress(data, size) {
+				Ok(d) => d,
+				Err(e) => anyhow::bail!("Error decompressing lz4: {}", e),
+			},
+		};
+		if decompressed.len() != size {
+			anyhow::bail!(
+				"Decompressed {} bytes, expected {}",
+				decompressed.len(),
+				size
+			);
+		}
+		Ok(decompressed)
+	}
+
+	// the first limit bytes of the uncompressed data, e.g. to sniff the type,
+	// only as much of stored is read as is needed for them
+	pub fn decompress_prefix(
+		&self,
+		stored: impl Read,
+		size: usize,
+		limit: usize,
+	) -> anyhow::Result<Vec<u8>> {
+		let limit = limit.min(size);
+		let mut data = Vec::with_capacity(limit);
+		match self {
+			Compression::Stored => {
+				stored.take(limit as u64).read_to_end(&mut data)?;
+			},
+			Compression::Deflate => {
+				DeflateDecoder::new(stored)
+					.take(limit as u64)
+					.read_to_end(&mut data)?;
+			},
+			Compression::Lz4 => Self::lz4_prefix(BufReader::new(stored), limit, &mut data)?,
+		}
+		if data.len() < limit {
+			anyhow::bail!(
+				"Decompressed {} bytes, expected at least {}",
+				data.len(),
+				limit
+			);
+		}
+		Ok(data)
+	}
+
+	// lz4_flex only decodes whole blocks, this walks the sequences until limit bytes are out
+	fn lz4_prefix(mut stored: impl Read, limit: usize, data: &mut Vec<u8>) -> anyhow::Result<()> {
+		while data.len() < limit {
+			let token = stored.read_u8()?;
+			let literals = Self::lz4_length(&mut stored, token >> 4)?;
+			let wanted = literals.min(limit - data.len());
+			let start = data.len();
+			(&mut stored).take(wanted as u64).read_to_end(data)?;
+			if data.len() - start < wanted {
+				anyhow::bail!("Truncated lz4 literals");
+			}
+			if data.len() >= limit {
+				break;
+			}
+			let offset = stored.read_u16::<LittleEndian>()? as usize;
+			let length = Self::lz4_length(&mut stored, token & 0x0f)? + 4;
+			if offset == 0 || offset > data.len() {
+				anyhow::bail!("Broken lz4 match offset {}", offset);
+			}
+			// matches can overlap their own output, so copy byte by byte
+			for _ in 0..length.min(limit - data.len()) {
+				data.push(data[data.len() - offset]);
+			}
+		}
+		Ok(())
+	}
+
+	// a 4 bit length, 15 continues with bytes, 255 continues further
+	fn lz4_length(stored: &mut impl Read, nibble: u8) -> anyhow::Result<usize> {
+		let mut length = nibble as usize;
+		if nibble == 0x0f {
+			loop {
+				let b = stored.read_u8()?;
+				length += b as usize;
+				if b != 0xff {
+					break;
+				}
+			}
+		}
+		Ok(length)
+	}
+
+	// the smallest result, entries that don't shrink stay stored
+	pub fn compress_best(data: &[u8]) -> anyhow::Result<(Compression, Vec<u8>)> {
+		let mut best = (Compression::Stored, Vec::new());
+		let mut best_size = data.len();
+		for compression in [Compression::Deflate, Compression::Lz4] {
+			let compressed = compression.compress(data)?;
+			if compressed.len() < best_size {
+				best_size = compressed.len();
+				best = (compression, compressed);
+			}
+		}
+		if best.0 == Compression::Stored {
+			best.1 = data.to_vec();
+		}
+		Ok(best)
+	}
+}
+
+impl core::fmt::Display for Compression {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		let name = match self {
+			Compression::Stored => "stored",
+			Compression::Deflate => "deflate",
+			Compression::Lz4 => "lz4",
+		};
+		write!(f, "{}", name)
+	}
+}
@@ -0,0 +1,73 @@
+use glob::Pattern;
+
+// Selects entries by name glob, or crc, and size range.
+// Entries matching any glob, or crc pass, without globs, and crcs all do, the size range always applies.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+	names:    Vec<Pattern>,
+	crcs:     Vec<u32>,
+	min_size: Option<u64>,
+	max_size: Option<u64>,
+}
+
+impl EntryFilter {
+	pub fn new() -> Self {
+		Self {
+			..Default::default()
+		}
+	}
+
+	pub fn add_name(&mut self, glob: &str) -> anyhow::Result<()> {
+		match Pattern::new(glob) {
+			Ok(p) => self.names.push(p),
+			Err(e) => anyhow::bail!("Broken glob {}: {}", glob, e),
+		}
+		Ok(())
+	}
+
+	pub fn add_crc(&mut self, crc: u32) {
+		self.crcs.push(crc);
+	}
+
+	pub fn set_min_size(&mut self, min_size: Option<u64>) {
+		self.min_size = min_size;
+	}
+
+	pub fn set_max_size(&mut self, max_size: Option<u64>) {
+		self.max_size = max_size;
+	}
+
+	pub fn has_names(&self) -> bool {
+		!self.names.is_empty()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.names.is_empty()
+			&& self.crcs.is_empty()
+			&& self.min_size.is_none()
+			&& self.max_size.is_none()
+	}
+
+	// entries without a name never match a glob
+	pub fn matches(&self, name: Option<&str>, crc: u32, size: u64) -> bool {
+		if self.min_size.is_some_and(|min| size < min)
+			|| self.max_size.is_some_and(|max| size > max)
+		{
+			return false;
+		}
+		if self.names.is_empty() && self.crcs.is_empty() {
+			return true;
+		}
+		self.crcs.contains(&crc)
+			|| name.is_some_and(|name| self.names.iter().any(|p| p.matches(name)))
+	}
+
+	// 0x1234abcd, or decimal
+	pub fn parse_crc(s: &str) -> Result<u32, String> {
+		let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+			Some(hex) => u32::from_str_radix(hex, 16),
+			None => s.parse::<u32>(),
+		};
+		result.map_err(|e| format!("Broken crc {}: {}", s, e))
+	}
+}
@@ -0,0 +1,35 @@
+// The order of the entries in the archive, sorting makes it independent of the paklist.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum EntryOrder {
+	#[default]
+	Paklist,
+	Crc,
+	Name,
+}
+
+impl std::str::FromStr for EntryOrder {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"paklist" => Ok(EntryOrder::Paklist),
+			"crc" => Ok(EntryOrder::Crc),
+			"name" => Ok(EntryOrder::Name),
+			_ => Err(format!(
+				"Unknown entry order {}, use paklist, crc, or name",
+				s
+			)),
+		}
+	}
+}
+
+impl core::fmt::Display for EntryOrder {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		let name = match self {
+			EntryOrder::Paklist => "paklist",
+			EntryOrder::Crc => "crc",
+			EntryOrder::Name => "name",
+		};
+		write!(f, "{}", name)
+	}
+}
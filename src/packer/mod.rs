@@ -1,6 +1,42 @@
 mod packer;
+pub use packer::Helper;
+
+mod align_rule;
+pub use align_rule::AlignRule;
+
+mod archive_reader;
+pub use archive_reader::{ArchiveEntry, ArchiveReader, EntryReader, PatchTarget};
+
+mod archive_stats;
+pub use archive_stats::{ArchiveStats, EntryStats, EntryType, GroupStats};
+
+mod archive_set;
+pub use archive_set::{ArchiveSet, OverlayEntry};
+
+mod compression;
+pub use compression::Compression;
+
+mod entry_filter;
+pub use entry_filter::EntryFilter;
+
+mod entry_order;
+pub use entry_order::EntryOrder;
+
+mod name_hash;
+pub use name_hash::NameHash;
+
+mod paklist;
+pub use paklist::Paklist;
+
+mod unpack_layout;
+pub use unpack_layout::UnpackLayout;
 
 pub mod command_packer;
+pub mod command_packer_apply;
+pub mod command_packer_cat;
+pub mod command_packer_diff;
 pub mod command_packer_list;
 pub mod command_packer_pack;
+pub mod command_packer_stats;
 pub mod command_packer_unpack;
+pub mod command_packer_verify;
@@ -0,0 +1,88 @@
+use crate::util::CrcHelper;
+
+static CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+static CRC32C: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
+
+// How names are turned into the crcs in the directory, alternatives for colliding names.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum NameHash {
+	#[default]
+	Crc32,
+	Crc32c,
+	Fnv1a,
+}
+
+impl NameHash {
+	pub fn from_u8(v: u8) -> anyhow::Result<NameHash> {
+		match v {
+			0 => Ok(NameHash::Crc32),
+			1 => Ok(NameHash::Crc32c),
+			2 => Ok(NameHash::Fnv1a),
+			v => anyhow::bail!("Unknown name hash {}", v),
+		}
+	}
+
+	pub fn as_u8(&self) -> u8 {
+		match self {
+			NameHash::Crc32 => 0,
+			NameHash::Crc32c => 1,
+			NameHash::Fnv1a => 2,
+		}
+	}
+
+	// 0 means no salt, crc32 without salt matches CrcHelper.
+	// The crcs are linear, names of the same length that collide keep colliding whatever is hashed before them,
+	// so a salt also seeds a non-linear multiply, xorshift pass over the name that is xored into the crc.
+	// For fnv-1a the salt is xored into the offset basis.
+	pub fn crc_from_name(&self, name: &str, salt: u32) -> u32 {
+		let clean_name = CrcHelper::clean_name_from_name(name);
+		let crc = match self {
+			NameHash::Crc32 => &CRC32,
+			NameHash::Crc32c => &CRC32C,
+			NameHash::Fnv1a => {
+				return clean_name.bytes().fold(0x811c9dc5 ^ salt, |h, b| {
+					(h ^ b as u32).wrapping_mul(0x01000193)
+				});
+			},
+		};
+		let mut digest = crc.digest();
+		if salt == 0 {
+			digest.update(clean_name.as_bytes());
+			return digest.finalize();
+		}
+		digest.update(&salt.to_le_bytes());
+		digest.update(clean_name.as_bytes());
+		let mixed = clean_name.bytes().fold(salt, |h, b| {
+			let h = (h ^ b as u32).wrapping_mul(0x9e3779b1);
+			h ^ (h >> 15)
+		});
+		digest.finalize() ^ mixed
+	}
+}
+
+impl std::str::FromStr for NameHash {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"crc32" => Ok(NameHash::Crc32),
+			"crc32c" => Ok(NameHash::Crc32c),
+			"fnv1a" => Ok(NameHash::Fnv1a),
+			_ => Err(format!(
+				"Unknown name hash {}, use crc32, crc32c, or fnv1a",
+				s
+			)),
+		}
+	}
+}
+
+impl core::fmt::Display for NameHash {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		let name = match self {
+			NameHash::Crc32 => "crc32",
+			NameHash::Crc32c => "crc32c",
+			NameHash::Fnv1a => "fnv1a",
+		};
+		write!(f, "{}", name)
+	}
+}
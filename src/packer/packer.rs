@@ -1,22 +1,51 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Write};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
 use std::string::String;
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use symlink::symlink_file;
+use byteorder::{LittleEndian, WriteBytesExt};
 
 use crate::name_map::NameMap;
+use crate::packer::archive_reader::FLAG_NAMES;
+use crate::packer::archive_reader::FLAG_TARGET;
+use crate::packer::archive_reader::FLAG_TOMBSTONES;
+use crate::packer::AlignRule;
+use crate::packer::ArchiveEntry;
+use crate::packer::ArchiveReader;
+use crate::packer::Compression;
+use crate::packer::EntryFilter;
+use crate::packer::EntryOrder;
+use crate::packer::NameHash;
+use crate::packer::Paklist;
+use crate::packer::PatchTarget;
+use crate::packer::UnpackLayout;
+use crate::util::CrcHelper;
+use crate::util::FileHelper;
+
+// where the data comes from when saving
+#[derive(Debug, Default)]
+enum Source {
+	#[default]
+	File,
+	Archive(Rc<ArchiveReader>, ArchiveEntry),
+}
 
 #[derive(Debug, Default)]
 pub struct Entry {
 	//	basepath: String,
-	filename:   String,
-	clean_name: Option<String>,
-	crc:        u32,
-	size:       u32,
-	pos:        u32,
-	data:       Vec<u8>,
+	source:      Source,
+	filename:    String,
+	clean_name:  Option<String>,
+	crc:         u32,
+	size:        u64,
+	pos:         u64,
+	data:        Vec<u8>,
+	compression: Compression,
+	stored_size: u64,
+	align:       Option<u32>, // instead of the align rules
 }
 
 impl core::fmt::Display for Entry {
@@ -32,38 +61,31 @@ impl core::fmt::Display for Entry {
 			} else {
 				&self.filename
 			},
-		)
+		)?;
+		if self.compression != Compression::Stored {
+			write!(f, " ({}, {} bytes)", self.compression, self.stored_size)?;
+		}
+		Ok(())
 	}
 }
 impl Entry {
-	fn create(basepath: &String, filename: &String) -> Entry {
+	fn create(
+		basepath: &String,
+		filename: &String,
+		name_hash: NameHash,
+		salt: u32,
+	) -> anyhow::Result<Entry> {
 		let fullfilename = format!("{}/{}", basepath, filename);
 
-		// :TODO: better error handling
-		let size = match fs::metadata(fullfilename) {
-			Ok(metadata) => metadata.len() as u32,
-			Err(_) => 0,
+		// only informative, the actual size is recorded when saving
+		let size = match fs::metadata(&fullfilename) {
+			Ok(metadata) if metadata.is_file() => metadata.len(),
+			Ok(_) => anyhow::bail!("Not a file: {}", fullfilename),
+			Err(e) => anyhow::bail!("Error opening {}: {}", fullfilename, e),
 		};
 
-		// :TODO: calculate actual CRC name
-		let downcase_name = filename.to_lowercase();
-		// Ruby: .gsub( /\W\./, ' ' ) // should be 'a-zA-Z0-9_', but actual code behaves differently
-		let clean_name: String = downcase_name
-			.chars()
-			.map(|c| match c {
-				'0'..='9' => c,
-				'a'..='z' => c,
-				//			'A'..='Z' => c,	// already downcase
-				'!'..='@' => c,
-				'['..='`' => c,
-				'{'..='~' => c,
-				//		0x7f => c,			// ignore DEL
-				_ => ' ',
-			})
-			.collect();
-
-		const CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
-		let crc = CRC32.checksum(clean_name.as_bytes());
+		let clean_name = CrcHelper::clean_name_from_name(filename);
+		let crc = name_hash.crc_from_name(filename, salt);
 
 		println!(
 			"CRC: {:?} -> {:?} crc: {:?} {:#10X}\n",
@@ -71,20 +93,21 @@ impl Entry {
 		);
 		//	      puts "CRC: " + filename + " -> " + name + " crc: " + @crc.to_s
 
-		Entry {
+		Ok(Entry {
 			//			basepath: basepath.to_string(),
 			filename: filename.to_string(),
 			clean_name: Some(clean_name),
 			crc: crc,
 			size: size,
 			..Default::default()
-		}
+		})
 	}
 
-	fn create_from_archive(crc: u32, pos: u32, size: u32) -> Entry {
+	fn create_from_archive(crc: u32, pos: u64, size: u64, name: Option<&str>) -> Entry {
 		Entry {
 			//basepath: String::new(),
 			filename: String::new(),
+			clean_name: name.map(|n| n.to_string()),
 			crc: crc,
 			size: size,
 			pos: pos,
@@ -92,14 +115,13 @@ impl Entry {
 		}
 	}
 
-	fn load_from_archive(&mut self, data: &Vec<u8>) -> bool {
-		// :TODO: find a more idomatic way
-		let start = self.pos as usize;
-		let end = start + self.size as usize;
-		self.data.resize(self.size as usize, 0);
-		self.data.clone_from_slice(&data[start..end]);
-
-		true
+	// the name as given, or from the archive
+	fn name(&self) -> Option<&str> {
+		if !self.filename.is_empty() {
+			Some(&self.filename)
+		} else {
+			self.clean_name.as_deref()
+		}
 	}
 
 	#[allow(dead_code)]
@@ -111,10 +133,21 @@ impl Entry {
 
 #[derive(Debug, Default)]
 pub struct Archive {
-	basepath:   String,
-	entries:    Vec<Entry>,
-	name_map:   Option<NameMap>,
-	names_only: bool,
+	basepath:    String,
+	entries:     Vec<Entry>,
+	name_map:    Option<NameMap>,
+	layout:      UnpackLayout,
+	filter:      EntryFilter,
+	compress:    bool,
+	embed_names: bool,
+	checksums:   bool,
+	name_hash:   NameHash,
+	salt:        u32,
+	align_rules: Vec<AlignRule>,
+	tombstones:  Option<Vec<u32>>,
+	target:      Option<PatchTarget>,
+	min_version: u8,
+	by_crc:      HashMap<u32, usize>,
 }
 
 impl Archive {
@@ -134,25 +167,166 @@ impl Archive {
 		self.name_map.take()
 	}
 
-	pub fn set_names_only(&mut self, names_only: bool) {
-		self.names_only = names_only;
+	pub fn set_layout(&mut self, layout: UnpackLayout) {
+		self.layout = layout;
 	}
 
-	pub fn add_entry(&mut self, filename: &String) -> bool {
-		let entry = Entry::create(&self.basepath, &filename);
-		if let Some(name_map) = &mut self.name_map {
-			if let Some(clean_name) = &entry.clean_name {
-				name_map.insert(entry.crc, clean_name.to_string());
+	// only for entries loaded afterwards
+	pub fn set_filter(&mut self, filter: EntryFilter) {
+		self.filter = filter;
+	}
+
+	pub fn set_compress(&mut self, compress: bool) {
+		self.compress = compress;
+	}
+
+	pub fn set_embed_names(&mut self, embed_names: bool) {
+		self.embed_names = embed_names;
+	}
+
+	pub fn set_checksums(&mut self, checksums: bool) {
+		self.checksums = checksums;
+	}
+
+	pub fn set_align_rules(&mut self, align_rules: &[AlignRule]) {
+		self.align_rules = align_rules.to_vec();
+	}
+
+	// name hash, alignment, checksums, compression, and names as in the reader
+	pub fn configure_like(&mut self, reader: &ArchiveReader) {
+		self.set_name_hash(reader.name_hash(), reader.salt());
+		self.align_rules = if reader.alignment() > 1 {
+			AlignRule::new(None, reader.alignment())
+				.into_iter()
+				.collect()
+		} else {
+			Vec::new()
+		};
+		self.checksums = reader.version() >= 4;
+		self.compress = reader
+			.entries()
+			.iter()
+			.any(|e| e.compression() != Compression::Stored);
+		self.embed_names = reader.has_names();
+	}
+
+	// makes this a patch archive, the entries with these crcs are removed when applying
+	pub fn set_tombstones(&mut self, tombstones: Vec<u32>) {
+		self.tombstones = Some(tombstones);
+	}
+
+	// stored in patch archives, for rebuilding the new archive when applying
+	pub fn set_target(&mut self, target: PatchTarget) {
+		self.target = Some(target);
+	}
+
+	// compression, checksums, names, and version of the new archive of a patch,
+	// the alignment is given per entry
+	pub fn configure_like_target(&mut self, target: &PatchTarget) {
+		self.compress = target.compress();
+		self.checksums = target.checksums();
+		self.embed_names = target.embed_names();
+		self.min_version = target.version();
+		self.align_rules = Vec::new();
+	}
+
+	// only for entries added afterwards
+	pub fn set_name_hash(&mut self, name_hash: NameHash, salt: u32) {
+		self.name_hash = name_hash;
+		self.salt = salt;
+	}
+
+	// fails for duplicated paths, and names with the same crc, they would shadow each other
+	pub fn add_entry(&mut self, filename: &String) -> anyhow::Result<()> {
+		let entry = Entry::create(&self.basepath, &filename, self.name_hash, self.salt)?;
+		if let Some(idx) = self.by_crc.get(&entry.crc) {
+			let other = &self.entries[*idx];
+			if other.clean_name == entry.clean_name {
+				anyhow::bail!(
+					"Duplicate path: {} and {} both clean to {:?}",
+					other.filename,
+					entry.filename,
+					entry.clean_name.as_deref().unwrap_or_default()
+				);
+			} else {
+				anyhow::bail!(
+					"Name collision: {} and {} both have crc {:#010x}, try a different --name-hash, or --salt",
+					other.filename,
+					entry.filename,
+					entry.crc
+				);
 			}
 		}
+		self.by_crc.insert(entry.crc, self.entries.len());
+		self.add_to_name_map(&entry);
+		self.entries.push(entry);
 
+		Ok(())
+	}
+
+	// the data is streamed from the reader when saving, with the crc (and name hash) of the reader
+	// the alignment overrides the align rules
+	pub fn add_entry_from_reader(
+		&mut self,
+		reader: &Rc<ArchiveReader>,
+		archive_entry: &ArchiveEntry,
+		align: Option<u32>,
+	) -> anyhow::Result<()> {
+		let entry = Entry {
+			source: Source::Archive(Rc::clone(reader), *archive_entry),
+			align,
+			clean_name: reader.name(archive_entry).map(|n| n.to_string()),
+			crc: archive_entry.crc(),
+			size: archive_entry.size(),
+			..Default::default()
+		};
+		if self.by_crc.contains_key(&entry.crc) {
+			anyhow::bail!("Duplicate crc {:#010x}", entry.crc);
+		}
+		self.by_crc.insert(entry.crc, self.entries.len());
+		self.add_to_name_map(&entry);
 		self.entries.push(entry);
 
-		true
+		Ok(())
+	}
+
+	// sorted by name, or crc, the tombstones too, so the output doesn't depend on the paklist order
+	pub fn sort_entries(&mut self, order: EntryOrder) {
+		match order {
+			EntryOrder::Paklist => return,
+			EntryOrder::Crc => self.entries.sort_by_key(|e| e.crc),
+			// crcs are unique, and break ties for entries without names
+			EntryOrder::Name => self
+				.entries
+				.sort_by(|a, b| (a.name(), a.crc).cmp(&(b.name(), b.crc))),
+		}
+		if let Some(tombstones) = &mut self.tombstones {
+			tombstones.sort();
+		}
+		self.by_crc = self
+			.entries
+			.iter()
+			.enumerate()
+			.map(|(idx, e)| (e.crc, idx))
+			.collect();
 	}
 
-	fn add_entry_from_archive(&mut self, crc: u32, pos: u32, size: u32) -> bool {
-		let mut entry = Entry::create_from_archive(crc, pos, size);
+	fn add_to_name_map(&mut self, entry: &Entry) {
+		if let Some(name_map) = &mut self.name_map {
+			if let Some(clean_name) = &entry.clean_name {
+				name_map.insert(entry.crc, clean_name.to_string());
+			}
+		}
+	}
+
+	fn add_entry_from_archive(
+		&mut self,
+		crc: u32,
+		pos: u64,
+		size: u64,
+		name: Option<&str>,
+	) -> bool {
+		let mut entry = Entry::create_from_archive(crc, pos, size, name);
 		if let Some(name_map) = &mut self.name_map {
 			if let Some(clean_name) = &entry.clean_name {
 				name_map.insert(entry.crc, clean_name.to_string());
@@ -170,167 +344,334 @@ impl Archive {
 		true
 	}
 
+	// Streams every file into the archive, the directory is written with placeholders first,
+	// and patched with the actually written sizes at the end.
+	// version 2, or
+	// version 3, entries are compressed with the method that shrinks them most, or stored,
+	// version 4 adds checksums, version 5 the salt, and name hash, version 6 the alignment,
+	// version 7 64 bit positions, and sizes
 	pub fn save(&self, output: &String) -> anyhow::Result<u32> {
-		// write output
-		let output_file = File::create(output);
-		// :TODO: rethink error handling
-		let mut output_file = match output_file {
-			Ok(p) => p,
-			Err(_e) => anyhow::bail!("Error writing file"),
-		};
+		let alignments: Vec<u32> = self
+			.entries
+			.iter()
+			.map(|e| {
+				e.align
+					.unwrap_or_else(|| AlignRule::align_for(&self.align_rules, &e.filename))
+			})
+			.collect();
+		let aligned = alignments.iter().any(|a| *a > 1);
+		// compression never grows entries, so this is an upper bound
+		let data_size: u64 = self
+			.entries
+			.iter()
+			.zip(&alignments)
+			.map(|(e, a)| e.size + *a as u64 - 1)
+			.sum();
+		let version = self.min_version.max(
+			if data_size > u32::MAX as u64 {
+				7
+			} else if aligned {
+				6
+			} else if self.hashes_names() {
+				5
+			} else if self.checksums {
+				4
+			} else if self.compress {
+				3
+			} else {
+				2
+			},
+		);
+		self.write(output, version, &alignments)
+	}
 
-		// :TODO: add error handling
-		let write_names = false;
-		let mut flags: u8 = 0;
-		if write_names {
-			flags |= 1
+	// only fails if the file grew while packing, the version is chosen from the sizes found when adding
+	fn check_fits_version(
+		version: u8,
+		filename: &str,
+		pos: u64,
+		stored_size: u64,
+		size: u64,
+	) -> anyhow::Result<()> {
+		if version < 7 && (pos + stored_size > u32::MAX as u64 || size > u32::MAX as u64) {
+			anyhow::bail!(
+				"Refusing to write a broken archive, {} grew to {} bytes while packing",
+				filename,
+				size
+			);
 		}
-		let number_of_files: u32 = self.entries.len() as u32;
-
-		output_file
-			.write_all(&[
-				0x4f, 0x4d, 0x41, 0x52,  // magic header
-				2,     // version
-				flags, // flags
-				0, 0, // reserved
-			])
-			.unwrap();
-		output_file
-			.write_u32::<LittleEndian>(number_of_files)
-			.unwrap();
-
-		// write the directory
-		let mut pos = 0;
-		for entry in &self.entries {
-			// crc, pos, size all as LittleEndian u32
-			output_file.write_u32::<LittleEndian>(entry.crc).unwrap();
-			output_file.write_u32::<LittleEndian>(pos).unwrap();
-			output_file.write_u32::<LittleEndian>(entry.size).unwrap();
+		Ok(())
+	}
 
-			pos += entry.size;
-		}
+	fn write(&self, output: &String, version: u8, alignments: &[u32]) -> anyhow::Result<u32> {
+		// guaranteed for all entries
+		let alignment = alignments.iter().copied().min().unwrap_or(1);
 
-		// write data to output
+		let mut output_file = match File::create(output) {
+			Ok(f) => BufWriter::new(f),
+			Err(e) => anyhow::bail!("Error writing {}: {}", output, e),
+		};
 
-		for entry in &self.entries {
-			let filename = format!("{}/{}", self.basepath, entry.filename);
-			//		println!("{:?}", filename );
-			let data_file = File::open(&filename);
-			// :TODO: rethink error handling
-			let mut data_file = match data_file {
-				Ok(p) => p,
-				Err(_e) => anyhow::bail!("Error reading data file: {}", &filename),
+		let mut flags = if self.embed_names { FLAG_NAMES } else { 0 };
+		if self.tombstones.is_some() {
+			flags |= FLAG_TOMBSTONES;
+		}
+		if self.target.is_some() {
+			flags |= FLAG_TARGET;
+		}
+		let number_of_files: u32 = self.entries.len() as u32;
+		output_file.write_all(&[
+			0x4f, 0x4d, 0x41, 0x52, // magic header
+			version, flags, 0, 0, // version, flags, reserved
+		])?;
+		output_file.write_u32::<LittleEndian>(number_of_files)?;
+		if version >= 5 {
+			output_file.write_u32::<LittleEndian>(self.salt)?;
+			let log2_alignment = if version >= 6 {
+				alignment.trailing_zeros() as u8
+			} else {
+				0
 			};
-			let mut buffer = Vec::<u8>::new();
-			data_file.read_to_end(&mut buffer).unwrap();
-			output_file.write_all(&buffer).unwrap();
+			output_file.write_all(&[self.name_hash.as_u8(), log2_alignment, 0, 0])?;
 		}
 
-		Ok(number_of_files)
-	}
-
-	pub fn load(&mut self, filename: &String) -> Result<u32, &'static str> {
-		let file = File::open(filename);
-
-		let file = match file {
-			Ok(p) => p,
-			Err(_e) => return Err("Error reading file"),
+		// crc, pos, size (v2)
+		// crc, pos, stored size, size, compression, 3 reserved, (checksum), as u64 since v7
+		let directory_entry_size = match version {
+			2 => 12,
+			3 => 20,
+			4..=6 => 24,
+			_ => 4 + 8 + 8 + 8 + 1 + 3 + 4,
 		};
-
-		let mut bufreader = BufReader::new(file);
-
-		// read header
-		// check magic
-		let magic = [0x4fu8, 0x4d, 0x41, 0x52];
-		for m in &magic {
-			let b = bufreader.read_u8().unwrap_or(0);
-			if b != *m {
-				return Err("Broken magic");
+		let directory_pos = output_file.stream_position()?;
+		output_file.write_all(&vec![0u8; self.entries.len() * directory_entry_size])?;
+
+		let mut directory = Vec::with_capacity(self.entries.len());
+		let data_start = output_file.stream_position()?;
+		let mut pos: u64 = 0;
+		for (entry, align) in self.entries.iter().zip(alignments) {
+			// absolute, for memory mapping
+			let padding = (data_start + pos).next_multiple_of(*align as u64) - (data_start + pos);
+			if padding > 0 {
+				output_file.write_all(&vec![0u8; padding as usize])?;
+				pos += padding;
 			}
+			let (filename, data_file): (String, Box<dyn Read>) = match &entry.source {
+				Source::File => {
+					let filename = format!("{}/{}", self.basepath, entry.filename);
+					match File::open(&filename) {
+						Ok(f) => (filename, Box::new(f)),
+						Err(e) => anyhow::bail!("Error reading data file: {}: {}", &filename, e),
+					}
+				},
+				Source::Archive(reader, archive_entry) => (
+					format!("{:#010x}", archive_entry.crc()),
+					Box::new(reader.open_entry(archive_entry)),
+				),
+			};
+			let mut data_file = ChecksumReader::new(data_file);
+			let (compression, stored_size, size) = if self.compress {
+				// one file at a time
+				let mut data = Vec::new();
+				data_file.read_to_end(&mut data)?;
+				let (compression, compressed) = Compression::compress_best(&data)?;
+				output_file.write_all(&compressed)?;
+				(compression, compressed.len() as u64, data.len() as u64)
+			} else {
+				let size = std::io::copy(&mut data_file, &mut output_file)?;
+				(Compression::Stored, size, size)
+			};
+			if size != entry.size {
+				println!(
+					"Warning: {} changed while packing, {} -> {} bytes",
+					filename, entry.size, size
+				);
+			}
+			println!(
+				"{}: {} -> {} bytes ({})",
+				entry.name().unwrap_or(&filename),
+				size,
+				stored_size,
+				compression
+			);
+
+			Self::check_fits_version(version, &filename, pos, stored_size, size)?;
+			directory.push((
+				entry.crc,
+				pos,
+				stored_size,
+				size,
+				compression,
+				data_file.checksum(),
+			));
+			pos += stored_size;
 		}
 
-		let v = bufreader.read_u8().unwrap_or(0);
-		if v != 2 {
-			return Err("Wrong version");
+		if self.embed_names {
+			self.write_names(&mut output_file)?;
 		}
-
-		let flags = bufreader.read_u8().unwrap_or(0);
-		if flags != 0 {
-			return Err(":TODO: Flags not implemented");
+		if let Some(tombstones) = &self.tombstones {
+			for crc in tombstones {
+				output_file.write_u32::<LittleEndian>(*crc)?;
+			}
+			output_file.write_u32::<LittleEndian>(tombstones.len() as u32 * 4)?;
+		}
+		if let Some(target) = &self.target {
+			target.write(&mut output_file)?;
 		}
 
-		for _reserved in 0..2 {
-			let r = bufreader.read_u8().unwrap_or(0);
-			if r != 0 {
-				return Err(":TODO: Reserved field not zero");
+		output_file.seek(SeekFrom::Start(directory_pos))?;
+		for (crc, pos, stored_size, size, compression, checksum) in directory {
+			output_file.write_u32::<LittleEndian>(crc)?;
+			if version >= 7 {
+				output_file.write_u64::<LittleEndian>(pos)?;
+				output_file.write_u64::<LittleEndian>(stored_size)?;
+				output_file.write_u64::<LittleEndian>(size)?;
+			} else {
+				// checked above
+				output_file.write_u32::<LittleEndian>(pos as u32)?;
+				output_file.write_u32::<LittleEndian>(stored_size as u32)?;
+				if version >= 3 {
+					output_file.write_u32::<LittleEndian>(size as u32)?;
+				}
+			}
+			if version >= 3 {
+				output_file.write_all(&[compression.as_u8(), 0, 0, 0])?;
+			}
+			if version >= 4 {
+				output_file.write_u32::<LittleEndian>(checksum)?;
 			}
 		}
+		output_file.flush()?;
 
-		let number_of_files = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
-		println!("Reading {:?} files from archive", number_of_files);
+		Ok(number_of_files)
+	}
 
-		for _e in 0..number_of_files {
-			let crc = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
-			let pos = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
-			let size = bufreader.read_u32::<LittleEndian>().unwrap_or(0);
-			self.add_entry_from_archive(crc, pos, size);
+	// the names as given in the paklist, in directory order, followed by the size of the table
+	fn write_names(&self, w: &mut impl Write) -> anyhow::Result<()> {
+		let mut table_size = 0u32;
+		for entry in &self.entries {
+			let name = match entry.name() {
+				Some(name) => name.as_bytes(),
+				None => anyhow::bail!("Name missing for {:#010x}, can't embed names", entry.crc),
+			};
+			if name.len() > u16::MAX as usize {
+				anyhow::bail!("Name too long for name table: {}", entry.filename);
+			}
+			w.write_u16::<LittleEndian>(name.len() as u16)?;
+			w.write_all(name)?;
+			table_size += 2 + name.len() as u32;
 		}
+		w.write_u32::<LittleEndian>(table_size)?;
+		Ok(())
+	}
+
+	// anything but the plain crc32 of the name needs version 5
+	fn hashes_names(&self) -> bool {
+		self.salt != 0 || self.name_hash != NameHash::Crc32
+	}
 
-		let mut data = Vec::new();
-		bufreader.read_to_end(&mut data).unwrap();
-		for entry in &mut self.entries {
-			(*entry).load_from_archive(&data);
+	pub fn load(&mut self, filename: &String) -> Result<u32, &'static str> {
+		let reader = match ArchiveReader::open(filename) {
+			Ok(r) => r,
+			Err(e) => {
+				println!("{}", e);
+				return Err("Error reading archive");
+			},
+		};
+		println!("Reading {:?} files from archive", reader.len());
+
+		let mut has_names = false;
+
+		for archive_entry in reader.entries() {
+			self.add_entry_from_archive(
+				archive_entry.crc(),
+				archive_entry.offset(),
+				archive_entry.size(),
+				reader.name(archive_entry),
+			);
+			// filtered entries are not read at all
+			if let Some(entry) = self.entries.last() {
+				has_names |= entry.clean_name.is_some();
+				if !self
+					.filter
+					.matches(entry.clean_name.as_deref(), entry.crc, entry.size)
+				{
+					self.entries.pop();
+					continue;
+				}
+			}
+			let data = match reader.read(archive_entry) {
+				Ok(data) => data,
+				Err(e) => {
+					println!("{}", e);
+					return Err("Error reading archive entry");
+				},
+			};
+			if let Some(entry) = self.entries.last_mut() {
+				entry.data = data;
+				entry.compression = archive_entry.compression();
+				entry.stored_size = archive_entry.stored_size();
+			}
+		}
+		if self.filter.has_names() && !has_names {
+			return Err("Name filters need names, embedded, or from a name map");
 		}
 
 		Ok(0)
 	}
 
-	pub fn unpack(&self, targetpath: &String) -> Result<u32, &'static str> {
+	// returns the entries that were skipped, and why, e.g. for unsafe names
+	pub fn unpack(&self, targetpath: &str) -> anyhow::Result<Vec<String>> {
+		let mut skipped = Vec::new();
+		let mut written = HashSet::new();
 		for entry in &self.entries {
-			let filename = format!("{}/{:#010X}", targetpath, entry.crc);
-			let clean_name = if let Some(name_map) = &self.name_map {
-				name_map.get_name(entry.crc)
-			} else {
-				None
+			let crc_name = format!("{:#010X}", entry.crc);
+			// embedded in the archive, or from the name map
+			let name = match (self.layout, entry.clean_name.as_ref()) {
+				(UnpackLayout::Crc, _) => crc_name.clone(),
+				(_, None) => {
+					println!("Name for {} not found, using crc name.", crc_name);
+					crc_name.clone()
+				},
+				(UnpackLayout::Tree, Some(name)) => name.clone(),
+				(UnpackLayout::Flat, Some(name)) => name.replace(['/', '\\'], "_"),
 			};
-			println!("{} <- {}", filename, clean_name.unwrap_or(&"".to_string()));
-
-			let filename = if !self.names_only {
-				filename
-			} else {
-				if let Some(clean_name) = clean_name {
-					format!("{}/{}", targetpath, clean_name)
-				} else {
-					println!("Name for {} not found, using crc name.", filename);
-					filename
-				}
-			};
-
-			let output_file = File::create(&filename);
-			// :TODO: rethink error handling
-			let mut output_file = match output_file {
-				Ok(p) => p,
-				Err(_e) => return Err("Error writing file"),
+			let path = match FileHelper::safe_relative_path(&name) {
+				Ok(path) => Path::new(targetpath).join(path),
+				Err(e) => {
+					skipped.push(format!("{}: {}", crc_name, e));
+					continue;
+				},
 			};
-
-			output_file.write_all(&entry.data).unwrap();
-
-			if !self.names_only {
-				if let Some(clean_name) = &clean_name {
-					let clean_name = format!("{}/{}", targetpath, clean_name);
-					let filename = format!("{:#010X}", entry.crc);
-					println!("Linking {} <- {}", filename, clean_name);
-					match symlink_file(filename, clean_name) {
-						Ok(()) => {},
-						Err(_e) => {},
-					}
-				// println!("Linking {:#010x} <- {}", entry.crc, clean_name);
-				} else {
-					// println!("Not linking {:#010x}, no name found in map.", entry.crc);
+			// e.g. a/b, and a_b in the flat layout
+			if !written.insert(path.clone()) {
+				skipped.push(format!("{}: {} unpacked twice", crc_name, name));
+				continue;
+			}
+			println!("{} <- {}", path.display(), crc_name);
+
+			if let Some(parent) = path.parent() {
+				if let Err(e) = fs::create_dir_all(parent) {
+					skipped.push(format!(
+						"{}: Error creating {}: {}",
+						crc_name,
+						parent.display(),
+						e
+					));
+					continue;
 				}
 			}
+			if let Err(e) = fs::write(&path, &entry.data) {
+				skipped.push(format!(
+					"{}: Error writing {}: {}",
+					crc_name,
+					path.display(),
+					e
+				));
+			}
 		}
-		Ok(0)
+		Ok(skipped)
 	}
 
 	pub fn entries(&self) -> std::slice::Iter<'_, Entry> {
@@ -338,25 +679,93 @@ impl Archive {
 	}
 }
 
-pub struct Helper {}
+// crc32 of everything read, for the directory
+struct ChecksumReader<R> {
+	inner:  R,
+	digest: crc::Digest<'static, u32>,
+}
 
-impl Helper {
-	pub fn filenames_in_file(filename: &String) -> Result<Vec<String>, &'static str> {
-		let file = File::open(filename);
+static DATA_CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
 
-		let file = match file {
-			Ok(p) => p,
-			Err(_e) => return Err("Error reading file"),
-		};
+impl<R: Read> ChecksumReader<R> {
+	fn new(inner: R) -> Self {
+		Self {
+			inner,
+			digest: DATA_CRC32.digest(),
+		}
+	}
 
-		let bufreader = BufReader::new(file);
+	fn checksum(self) -> u32 {
+		self.digest.finalize()
+	}
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let n = self.inner.read(buf)?;
+		self.digest.update(&buf[..n]);
+		Ok(n)
+	}
+}
 
-		let mut files: Vec<String> = Vec::new();
-		for line in bufreader.lines() {
-			let filename = line.unwrap();
-			files.push(filename);
+pub struct Helper {}
+
+impl Helper {
+	// globs are matched relative to the current directory
+	#[deprecated(note = "use Paklist, which also reports where a paklist is broken")]
+	pub fn filenames_in_file(filename: &String) -> Result<Vec<String>, &'static str> {
+		let mut paklist = Paklist::new(".");
+		match paklist.load(filename) {
+			Ok(()) => Ok(paklist.into_filenames()),
+			Err(_e) => Err("Error reading file"),
 		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn large_archives_use_64_bit_offsets() -> anyhow::Result<()> {
+		let dir = std::env::temp_dir().join("omt-test").join("packer-unit");
+		fs::create_dir_all(&dir)?;
+		fs::write(dir.join("a.txt"), b"first")?;
+		fs::write(dir.join("b.txt"), b"second")?;
+
+		let mut archive = Archive::create(&dir.to_string_lossy());
+		archive.set_embed_names(true);
+		archive.add_entry(&"a.txt".to_string())?;
+		archive.add_entry(&"b.txt".to_string())?;
+		// the version is chosen from the sizes found when adding, pretend b.txt is huge
+		archive.entries[1].size = u32::MAX as u64;
+		let output = dir.join("large.omar").to_string_lossy().to_string();
+		assert_eq!(2, archive.save(&output)?);
+
+		let reader = ArchiveReader::open(&output)?;
+		assert_eq!(7, reader.version());
+		let entry = *reader.find("b.txt").unwrap();
+		assert_eq!(b"second".to_vec(), reader.read(&entry)?);
+		assert_eq!(Some("b.txt"), reader.name(&entry));
+		assert_eq!(
+			b"first".to_vec(),
+			reader.read(reader.find("a.txt").unwrap())?
+		);
 
-		Ok(files)
+		// small archives stay at version 2
+		archive.entries[1].size = 6;
+		archive.save(&output)?;
+		assert_eq!(2, ArchiveReader::open(&output)?.version());
+
+		// files that grew past 32 bits while packing are refused below version 7
+		let max = u32::MAX as u64;
+		assert!(Archive::check_fits_version(6, "a.txt", 0, max, max).is_ok());
+		assert!(Archive::check_fits_version(6, "a.txt", 1, max, max).is_err());
+		// compressed well, but too large unpacked
+		assert!(Archive::check_fits_version(6, "a.txt", 0, 1024, max + 1).is_err());
+		assert!(Archive::check_fits_version(2, "a.txt", 0, max + 1, max + 1).is_err());
+		assert!(Archive::check_fits_version(7, "a.txt", max, max + 1, max * 2).is_ok());
+
+		Ok(())
 	}
 }
@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+// `*` stays within a directory, `**/` crosses them
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+	case_sensitive:              true,
+	require_literal_separator:   true,
+	require_literal_leading_dot: false,
+};
+
+const IGNORE_FILE: &str = ".omtignore";
+
+// The files to pack, relative to the basepath, from paklists, or the basepath itself.
+//
+// Paklist lines are names, `# comments`, globs like `fonts/**/*.omfont`,
+// `!globs` excluding files listed before, and `@include other.paklist` relative to the paklist.
+#[derive(Debug, Default)]
+pub struct Paklist {
+	basepath:  PathBuf,
+	filenames: Vec<String>,
+	known:     HashSet<String>,
+	files:     Option<Vec<String>>, // below the basepath, for globs
+	includes:  Vec<PathBuf>,        // to detect cycles
+}
+
+impl Paklist {
+	pub fn new(basepath: &str) -> Self {
+		Self {
+			basepath: PathBuf::from(basepath),
+			..Default::default()
+		}
+	}
+
+	pub fn filenames(&self) -> &[String] {
+		&self.filenames
+	}
+
+	pub fn into_filenames(self) -> Vec<String> {
+		self.filenames
+	}
+
+	pub fn load(&mut self, paklist: impl AsRef<Path>) -> anyhow::Result<()> {
+		let paklist = paklist.as_ref();
+		let content = match fs::read_to_string(paklist) {
+			Ok(c) => c,
+			Err(e) => anyhow::bail!("Error reading paklist {}: {}", paklist.display(), e),
+		};
+		let key = fs::canonicalize(paklist).unwrap_or_else(|_| paklist.to_path_buf());
+		if self.includes.contains(&key) {
+			anyhow::bail!("Include cycle: {} includes itself", paklist.display());
+		}
+		self.includes.push(key);
+		let result = self.parse(paklist, &content);
+		self.includes.pop();
+		result
+	}
+
+	fn parse(&mut self, paklist: &Path, content: &str) -> anyhow::Result<()> {
+		for (nr, line) in content.lines().enumerate() {
+			let line = line.trim();
+			let location = || format!("{}:{}", paklist.display(), nr + 1);
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			} else if let Some(include) = line.strip_prefix("@include ") {
+				let include = include.trim();
+				let include = paklist.parent().unwrap_or(Path::new("")).join(include);
+				if let Err(e) = self.load(&include) {
+					anyhow::bail!("{}: {}", location(), e);
+				}
+			} else if line.starts_with('@') {
+				anyhow::bail!("{}: Unknown directive {}", location(), line);
+			} else if let Some(exclude) = line.strip_prefix('!') {
+				let pattern = Self::pattern(exclude.trim(), &location)?;
+				self.filenames
+					.retain(|f| !pattern.matches_with(f, MATCH_OPTIONS));
+				self.known
+					.retain(|f| !pattern.matches_with(f, MATCH_OPTIONS));
+			} else if line.contains(['*', '?', '[']) {
+				let pattern = Self::pattern(line, &location)?;
+				let matching: Vec<String> = self
+					.files()?
+					.iter()
+					.filter(|f| pattern.matches_with(f, MATCH_OPTIONS))
+					.cloned()
+					.collect();
+				if matching.is_empty() {
+					println!("Warning: {} matches no files in {}", line, location());
+				}
+				for filename in matching {
+					self.push(filename);
+				}
+			} else {
+				// missing files are reported when packing
+				self.push(line.to_string());
+			}
+		}
+		Ok(())
+	}
+
+	// every file below the basepath, except the ones ignored by its .omtignore
+	pub fn add_dir(&mut self) -> anyhow::Result<()> {
+		let ignore_file = self.basepath.join(IGNORE_FILE);
+		let rules = match fs::read_to_string(&ignore_file) {
+			Ok(content) => IgnoreRule::parse(&content, &ignore_file.to_string_lossy())?,
+			Err(_) => Vec::new(),
+		};
+		let mut filenames = Vec::new();
+		Self::walk(&self.basepath, "", &rules, &mut filenames)?;
+		for filename in filenames {
+			if filename != IGNORE_FILE {
+				self.push(filename);
+			}
+		}
+		Ok(())
+	}
+
+	fn push(&mut self, filename: String) {
+		if self.known.insert(filename.clone()) {
+			self.filenames.push(filename);
+		}
+	}
+
+	fn pattern(glob: &str, location: &dyn Fn() -> String) -> anyhow::Result<Pattern> {
+		match Pattern::new(glob) {
+			Ok(p) => Ok(p),
+			Err(e) => anyhow::bail!("{}: Broken glob {}: {}", location(), glob, e),
+		}
+	}
+
+	fn files(&mut self) -> anyhow::Result<&[String]> {
+		if self.files.is_none() {
+			let mut files = Vec::new();
+			Self::walk(&self.basepath, "", &[], &mut files)?;
+			self.files = Some(files);
+		}
+		Ok(self.files.as_deref().unwrap_or_default())
+	}
+
+	// sorted, so the result doesn't depend on the filesystem
+	fn walk(
+		dir: &Path,
+		prefix: &str,
+		rules: &[IgnoreRule],
+		filenames: &mut Vec<String>,
+	) -> anyhow::Result<()> {
+		let entries = match fs::read_dir(dir) {
+			Ok(e) => e,
+			Err(e) => anyhow::bail!("Error reading directory {}: {}", dir.display(), e),
+		};
+		let mut entries = entries.collect::<Result<Vec<_>, _>>()?;
+		entries.sort_by_key(|e| e.file_name());
+		for entry in entries {
+			let name = entry.file_name().to_string_lossy().to_string();
+			let path = format!("{}{}", prefix, name);
+			// symlinked directories are not followed, symlinked files are
+			if entry.file_type()?.is_dir() {
+				if !IgnoreRule::ignored(rules, &path, true) {
+					Self::walk(&entry.path(), &format!("{}/", path), rules, filenames)?;
+				}
+			} else if entry.path().is_file() && !IgnoreRule::ignored(rules, &path, false) {
+				filenames.push(path);
+			}
+		}
+		Ok(())
+	}
+}
+
+// A subset of .gitignore, globs with a `/` match the whole path, others only the name,
+// a trailing `/` only matches directories, and `!` includes again, the last matching rule wins.
+#[derive(Debug)]
+struct IgnoreRule {
+	pattern:  Pattern,
+	path:     bool,
+	dir_only: bool,
+	negate:   bool,
+}
+
+impl IgnoreRule {
+	fn parse(content: &str, filename: &str) -> anyhow::Result<Vec<IgnoreRule>> {
+		let mut rules = Vec::new();
+		for (nr, line) in content.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (negate, line) = match line.strip_prefix('!') {
+				Some(l) => (true, l),
+				None => (false, line),
+			};
+			let (dir_only, line) = match line.strip_suffix('/') {
+				Some(l) => (true, l),
+				None => (false, line),
+			};
+			let path = line.contains('/');
+			let line = line.trim_start_matches('/');
+			let location = || format!("{}:{}", filename, nr + 1);
+			let pattern = Paklist::pattern(line, &location)?;
+			rules.push(IgnoreRule {
+				pattern,
+				path,
+				dir_only,
+				negate,
+			});
+		}
+		Ok(rules)
+	}
+
+	fn matches(&self, path: &str, is_dir: bool) -> bool {
+		if self.dir_only && !is_dir {
+			return false;
+		}
+		let name = if self.path {
+			path
+		} else {
+			path.rsplit('/').next().unwrap_or(path)
+		};
+		self.pattern.matches_with(name, MATCH_OPTIONS)
+	}
+
+	fn ignored(rules: &[IgnoreRule], path: &str, is_dir: bool) -> bool {
+		rules
+			.iter()
+			.rev()
+			.find(|r| r.matches(path, is_dir))
+			.map(|r| !r.negate)
+			.unwrap_or(false)
+	}
+}
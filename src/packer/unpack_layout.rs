@@ -0,0 +1,35 @@
+// How unpacked entries are named, entries without a name always use their crc.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum UnpackLayout {
+	// 0x1234ABCD
+	#[default]
+	Crc,
+	// textures/player.png, with nested directories
+	Tree,
+	// textures_player.png, all in the target path
+	Flat,
+}
+
+impl std::str::FromStr for UnpackLayout {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"crc" => Ok(UnpackLayout::Crc),
+			"tree" => Ok(UnpackLayout::Tree),
+			"flat" => Ok(UnpackLayout::Flat),
+			_ => Err(format!("Unknown layout {}, use crc, tree, or flat", s)),
+		}
+	}
+}
+
+impl core::fmt::Display for UnpackLayout {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+		let name = match self {
+			UnpackLayout::Crc => "crc",
+			UnpackLayout::Tree => "tree",
+			UnpackLayout::Flat => "flat",
+		};
+		write!(f, "{}", name)
+	}
+}
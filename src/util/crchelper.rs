@@ -44,6 +44,14 @@ impl CrcHelper {
 				@crc = Zlib::crc32( name.downcase )
 	*/
 	pub fn crc_from_name(name: &str) -> u32 {
+		let clean_name = CrcHelper::clean_name_from_name(name);
+		const CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+		let crc = CRC32.checksum(clean_name.as_bytes());
+
+		crc
+	}
+
+	pub fn clean_name_from_name(name: &str) -> String {
 		let downcase_name = name.to_lowercase();
 		// Ruby: .gsub( /\W\./, ' ' ) // should be 'a-zA-Z0-9_', but actual code behaves differently
 		let clean_name: String = downcase_name
@@ -59,9 +67,13 @@ impl CrcHelper {
 				_ => ' ',
 			})
 			.collect();
-		const CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
-		let crc = CRC32.checksum(clean_name.as_bytes());
 
-		crc
+		clean_name
+	}
+
+	// content checksum, e.g. for archive entries
+	pub fn crc_from_data(data: &[u8]) -> u32 {
+		const CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+		CRC32.checksum(data)
 	}
 }
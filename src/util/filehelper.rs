@@ -1,5 +1,6 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 
 pub struct FileHelper {}
 
@@ -22,4 +23,26 @@ impl FileHelper {
 
 		Ok(lines)
 	}
+
+	// for names from archives, or name maps, rejects absolute paths, `.`, and `..`,
+	// on windows also drives, and streams, `:` is a valid file name character elsewhere
+	pub fn safe_relative_path(name: &str) -> anyhow::Result<PathBuf> {
+		if name.is_empty() {
+			anyhow::bail!("Empty name");
+		}
+		if name.starts_with(['/', '\\']) {
+			anyhow::bail!("Absolute path: {}", name);
+		}
+		let mut path = PathBuf::new();
+		for component in name.split(['/', '\\']) {
+			match component {
+				"" | "." => anyhow::bail!("Empty path component: {}", name),
+				".." => anyhow::bail!("Path traversal: {}", name),
+				#[cfg(windows)]
+				c if c.contains(':') => anyhow::bail!("Drive, or stream in path: {}", name),
+				c => path.push(c),
+			}
+		}
+		Ok(path)
+	}
 }
@@ -6,6 +6,9 @@ mod test {
 
 		use omt::atlas::Atlas;
 		use omt::atlas::AtlasSet;
+		use omt::font::Font;
+		use omt::font::FontOptions;
+		use omt::font::RenderMode;
 
 		fn temp_output(name: &str) -> (PathBuf, PathBuf) {
 			let test_dir = std::env::temp_dir();
@@ -146,5 +149,42 @@ mod test {
 
 			Ok(())
 		}
+
+		#[test]
+		fn v2_combine_with_font_glyphs_works() -> anyhow::Result<()> {
+			let (test_dir, output) = temp_output("v2-font-%d");
+			let (_test_dir, font_output) = temp_output("v2-font-glyphs");
+			let font_name = font_output.to_string_lossy().to_string();
+
+			let options = FontOptions::default()
+				.with_render_mode(RenderMode::Bitmap)
+				.with_glyphs_only(true);
+			Font::create_with_options(&font_name, &options, &["Data/test-5x7.bdf"])?;
+
+			let (_font, page_count) = Font::load_metrics(&font_name)?;
+			assert_eq!(0, page_count);
+
+			let mut atlas_set = AtlasSet::default()
+				.with_target_size(128)
+				.with_border(0)
+				.with_inputs([PathBuf::from("Data/64x64_red.png").as_path()].to_vec())
+				.with_fonts([font_output.as_path()].to_vec());
+			let l = atlas_set.refit()?;
+			assert_eq!(1, l);
+
+			let r = atlas_set.save(&output, Some(&test_dir))?;
+			assert_eq!(1, r);
+
+			// the font now references the shared atlas page
+			let (font, page_count) = Font::load_metrics(&font_name)?;
+			assert_eq!(1, page_count);
+			let a = &font.glyphs['A' as usize];
+			assert_eq!(0, a.page);
+			assert_eq!(5.0 / 128.0, a.matrix[0]);
+			assert_eq!(7.0 / 128.0, a.matrix[4]);
+			assert!(test_dir.join("v2-font-glyphs.omtr").exists());
+
+			Ok(())
+		}
 	}
 }
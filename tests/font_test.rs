@@ -0,0 +1,536 @@
+mod test {
+	#[allow(non_snake_case)]
+	mod Font {
+		use std::io::Write;
+		use std::path::PathBuf;
+
+		use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+		use image::GenericImageView;
+		use omt::font::BitmapFont;
+		use omt::font::CodepointCollector;
+		use omt::font::Font;
+		use omt::font::FontEffects;
+		use omt::font::FontOptions;
+		use omt::font::RenderMode;
+		use omt::font::SizeMode;
+		use omt::font::TextAlign;
+		use omt::font::TextLayout;
+
+		fn temp_output(name: &str) -> (PathBuf, PathBuf) {
+			let test_dir = std::env::temp_dir();
+			let test_dir = test_dir.join("omt-test");
+			let test_dir = test_dir.join("font");
+
+			std::fs::create_dir_all(&test_dir).unwrap();
+			let output = test_dir.join(name);
+			eprintln!("{:?}", &output);
+			(test_dir, output)
+		}
+
+		#[test]
+		fn bdf_loading_works() -> anyhow::Result<()> {
+			let font = BitmapFont::load("Data/test-5x7.bdf")?;
+			assert_eq!(9, font.pixel_size);
+			assert_eq!(7, font.glyphs.len());
+
+			let a = font.glyph('A' as u32).unwrap();
+			assert_eq!((5, 7, 6), (a.width, a.height, a.advance));
+			assert_eq!(0, a.get(0, 0));
+			assert_eq!(255, a.get(1, 0));
+			assert_eq!(255, a.get(4, 3));
+
+			let g = font.glyph('g' as u32).unwrap();
+			assert_eq!(-2, g.y_offset);
+
+			Ok(())
+		}
+
+		#[test]
+		fn bitmap_font_from_bdf_works() -> anyhow::Result<()> {
+			let (_test_dir, output) = temp_output("bitmap-bdf");
+			let output = output.to_string_lossy().to_string();
+
+			let options = FontOptions::default()
+				.with_texsize(64)
+				.with_render_mode(RenderMode::Bitmap);
+			Font::create_with_options(&output, &options, &["Data/test-5x7.bdf"])?;
+
+			let font = Font::load(&output)?;
+			assert_eq!(128, font.glyphs.len());
+			let a = &font.glyphs['A' as usize];
+			assert_eq!((5, 7, 6), (a.width, a.height, a.advance));
+			// stored in the font, for rendering
+			assert_eq!(RenderMode::Bitmap, font.render_mode());
+			assert_eq!(7, Font::info(&output, None)?.version);
+
+			// 1-bit, white with coverage in alpha only
+			for y in 0..a.height {
+				for x in 0..a.width {
+					let p = font.pages[0].get_pixel(a.x + x, a.y + y);
+					assert!(p[3] == 0 || p[3] == 255);
+					if p[3] == 255 {
+						assert_eq!([255, 255, 255], [p[0], p[1], p[2]]);
+					}
+				}
+			}
+
+			Ok(())
+		}
+
+		#[test]
+		fn multi_page_font_works() -> anyhow::Result<()> {
+			let (test_dir, output) = temp_output("multi-page");
+			let output = output.to_string_lossy().to_string();
+
+			let options = FontOptions::default()
+				.with_texsize(8)
+				.with_render_mode(RenderMode::Bitmap);
+			Font::create_with_options(&output, &options, &["Data/test-5x7.bdf"])?;
+
+			// only one 5x7 glyph fits on each page
+			assert!(test_dir.join("multi-page-0.png").exists());
+			assert!(test_dir.join("multi-page-5.png").exists());
+
+			let font = Font::load(&output)?;
+			assert_eq!(6, font.pages.len());
+			assert_eq!(5, font.glyphs['g' as usize].page);
+			assert!(std::ptr::eq(&font.pages[0], font.image().unwrap()));
+			// metrics only, without the pages
+			assert!(Font::load_metrics(&output)?.0.image().is_none());
+
+			Ok(())
+		}
+
+		#[test]
+		fn multi_page_font_with_maximum_texsize_uses_single_page() -> anyhow::Result<()> {
+			let (test_dir, output) = temp_output("multi-page-maximum-texsize");
+			let output = output.to_string_lossy().to_string();
+
+			let options = FontOptions::default()
+				.with_texsize(8)
+				.with_maximum_texsize(64)
+				.with_render_mode(RenderMode::Bitmap);
+			Font::create_with_options(&output, &options, &["Data/test-5x7.bdf"])?;
+
+			assert!(test_dir.join("multi-page-maximum-texsize.png").exists());
+
+			let font = Font::load(&output)?;
+			assert_eq!(1, font.pages.len());
+			assert_eq!(16, font.pages[0].dimensions().0);
+			assert_eq!(Some(16), font.image().map(|i| i.dimensions().0));
+
+			Ok(())
+		}
+
+		#[test]
+		fn font_effects_are_stored_in_omfont() -> anyhow::Result<()> {
+			let (_test_dir, output) = temp_output("effects");
+			let output = output.to_string_lossy().to_string();
+
+			let effects = FontEffects::default()
+				.with_outline(2.0, FontEffects::parse_color("ff0000").unwrap())
+				.with_shadow((1.0, -1.0), 1.5, 0x00000080)
+				.with_glow(3.0, 0xffffffff);
+			let options = FontOptions::default()
+				.with_texsize(256)
+				.with_effects(effects.clone());
+			Font::create_with_options(&output, &options, &["Data/test-5x7.bdf"])?;
+
+			let font = Font::load(&output)?;
+			let loaded = font.effects().unwrap();
+			assert_eq!(0xff0000ff, loaded.outline_color);
+			assert_eq!(effects.shadow_offset, loaded.shadow_offset);
+			assert_eq!(effects.glow_radius, loaded.glow_radius);
+
+			// glyphs are padded to make room for the effects
+			let a = &font.glyphs['A' as usize];
+			assert!(a.width >= 5 + 2 * effects.padding());
+
+			Ok(())
+		}
+
+		fn bitmap_test_font(name: &str) -> anyhow::Result<Font> {
+			let (_test_dir, output) = temp_output(name);
+			let output = output.to_string_lossy().to_string();
+
+			let options = FontOptions::default()
+				.with_texsize(64)
+				.with_render_mode(RenderMode::Bitmap);
+			Font::create_with_options(&output, &options, &["Data/test-5x7.bdf"])?;
+			Font::load(&output)
+		}
+
+		#[test]
+		fn text_layout_works() -> anyhow::Result<()> {
+			let font = bitmap_test_font("layout")?;
+
+			let block = TextLayout::default().layout(&font, "AB\nA g");
+			assert_eq!(2, block.lines);
+			assert_eq!(4, block.quads.len());
+			// advance is 6 for all glyphs, lines are 9 apart
+			assert_eq!((0.0, 6.0), (block.quads[0].x, block.quads[1].x));
+			assert_eq!(block.quads[0].y + 9.0, block.quads[2].y);
+			assert_eq!(18.0, block.width);
+			// descender below the baseline
+			assert_eq!(
+				block.quads[2].y + 9.0,
+				block.quads[3].y + block.quads[3].height
+			);
+
+			Ok(())
+		}
+
+		#[test]
+		fn text_layout_wraps_and_aligns() -> anyhow::Result<()> {
+			let font = bitmap_test_font("layout-wrap")?;
+
+			let block = TextLayout::default()
+				.with_max_width(20.0)
+				.with_align(TextAlign::Right)
+				.layout(&font, "AB A");
+			assert_eq!(2, block.lines);
+			assert_eq!(12.0, block.width);
+			assert_eq!(6.0, block.quads[2].x);
+
+			let block = TextLayout::default()
+				.with_align(TextAlign::Center)
+				.with_scale(2.0)
+				.layout(&font, "A\nABB");
+			assert_eq!(12.0, block.quads[0].x);
+
+			let img = TextLayout::default().render(&font, "HI!", 0xff0000ff, 0x00000000)?;
+			assert_eq!(18, img.dimensions().0);
+			let covered = img.pixels().filter(|(_, _, p)| p[3] == 255).count();
+			assert!(covered > 0);
+
+			Ok(())
+		}
+
+		#[test]
+		fn text_layout_threshold_and_softness_work() -> anyhow::Result<()> {
+			let (_test_dir, output) = temp_output("layout-sdf");
+			let output = output.to_string_lossy().to_string();
+			// texsize, size, border, distancefield scale, and max distance, as before the options
+			Font::create(&output, 128, 16, 0, 4, 2, &["Data/test-5x7.bdf"])?;
+			let font = Font::load(&output)?;
+			assert_eq!(RenderMode::DistanceField, font.render_mode());
+			assert_eq!(2, Font::info(&output, None)?.version);
+
+			let covered = |layout: TextLayout| -> anyhow::Result<(usize, usize)> {
+				let img = layout
+					.with_scale(4.0)
+					.render(&font, "HI", 0xffffffff, 0x00000000)?;
+				let full = img.pixels().filter(|(_, _, p)| p[3] == 255).count();
+				let partial = img
+					.pixels()
+					.filter(|(_, _, p)| p[3] > 0 && p[3] < 255)
+					.count();
+				Ok((full, partial))
+			};
+			let (full, _partial) = covered(TextLayout::default())?;
+			let (thin, _partial) = covered(TextLayout::default().with_threshold(0.9))?;
+			assert!(full > 0);
+			assert!(thin < full);
+			// no antialiasing without softness
+			let (_full, partial) = covered(TextLayout::default().with_softness(0.0))?;
+			assert_eq!(0, partial);
+
+			Ok(())
+		}
+
+		#[test]
+		fn legacy_font_convert_works() -> anyhow::Result<()> {
+			let (_test_dir, output) = temp_output("legacy");
+			let legacy = output.to_string_lossy().to_string();
+			let converted = format!("{}-converted", legacy);
+
+			// different for every glyph, in texels of a 256 texture
+			let rect = |c: u32| (c * 2, (c % 4) * 16, 1 + c % 8, 4 + c % 5);
+			let bottom = |c: u32| -((c % 3) as f32);
+
+			// OMFN, size, 128 * 4 vertices with uv, and xyz, 128 advances
+			let mut data = Vec::new();
+			data.write_all(b"OMFN")?;
+			data.write_u32::<LittleEndian>(16)?;
+			for c in 0..128 {
+				let (x, y, w, h) = rect(c);
+				let (l, t) = (x as f32 / 256.0, y as f32 / 256.0);
+				let (w, h) = (w as f32 / 256.0, h as f32 / 256.0);
+				let top = bottom(c) + 16.0;
+				// upper left, lower left, upper right, lower right
+				for (u, v, y) in [
+					(l, t, top),
+					(l, t + h, bottom(c)),
+					(l + w, t, top),
+					(l + w, t + h, bottom(c)),
+				] {
+					data.write_f32::<LittleEndian>(u)?;
+					data.write_f32::<LittleEndian>(v)?;
+					data.write_f32::<LittleEndian>(0.0)?;
+					data.write_f32::<LittleEndian>(y)?;
+					data.write_f32::<LittleEndian>(0.0)?;
+				}
+			}
+			for c in 0..128 {
+				data.write_u16::<LittleEndian>(c)?;
+			}
+			std::fs::write(format!("{}.omfont", legacy), data)?;
+
+			// the texture is needed for the texsize
+			assert!(Font::convert(&legacy, &converted, None).is_err());
+			assert_eq!(2, Font::convert(&legacy, &converted, Some(256))?);
+
+			let info = Font::info(&converted, Some(256))?;
+			assert_eq!(("OMFONT", 2), (info.format.as_str(), info.version));
+			assert_eq!(16, info.size);
+			assert_eq!(128, info.glyphs.len());
+			for (c, g) in info.glyphs.iter().enumerate() {
+				let c = c as u32;
+				let (x, y, w, h) = rect(c);
+				assert_eq!((c, x, y, w, h), (g.codepoint, g.x, g.y, g.width, g.height));
+				assert_eq!(c, g.advance as u32);
+				assert_eq!(-bottom(c) / 256.0, g.y_offset);
+			}
+
+			// broken files are reported, not ignored
+			std::fs::write(format!("{}-broken.omfont", legacy), b"OMFONT\x02\x00")?;
+			assert!(Font::info(&format!("{}-broken", legacy), Some(256)).is_err());
+
+			Ok(())
+		}
+
+		#[test]
+		fn codepoint_collector_works() -> anyhow::Result<()> {
+			let (test_dir, _output) = temp_output("collector");
+			let files = [
+				("strings.csv", "key,en,de\ngreeting,\"Hi, you\",Grüße\n"),
+				("strings.json", r#"{ "greeting": ["ñ", { "nested": "ß" }] }"#),
+				("strings.yaml", "greeting: Ça\nlist:\n  - ø\n"),
+				(
+					"strings.po",
+					"msgid \"\"\nmsgstr \"Last-Translator: Zoë\"\n\nmsgid \"Welcome\"\nmsgstr \"\"\n\"Hallå\"\n\nmsgid \"Untranslated\"\nmsgstr \"\"\n",
+				),
+			];
+			let mut collector = CodepointCollector::default();
+			for (name, content) in files {
+				let filename = test_dir.join(name);
+				std::fs::write(&filename, content)?;
+				collector.add_file(&filename)?;
+			}
+			let codepoints = collector.codepoints();
+			for c in ['H', ',', 'ü', 'ñ', 'ß', 'Ç', 'ø', 'å', 'U'] {
+				assert!(codepoints.contains(&(c as u32)), "{:?} missing", c);
+			}
+			// keys, the po header, and translated msgids are not used
+			for c in ['\n', '{', 'ë', 'L', 'W'] {
+				assert!(!codepoints.contains(&(c as u32)), "{:?} found", c);
+			}
+
+			Ok(())
+		}
+
+		#[test]
+		fn subset_font_works() -> anyhow::Result<()> {
+			let (_test_dir, output) = temp_output("subset");
+			let output = output.to_string_lossy().to_string();
+
+			let mut collector = CodepointCollector::default();
+			collector.add_text("BAg €");
+			let options = FontOptions::default()
+				.with_texsize(64)
+				.with_render_mode(RenderMode::Bitmap)
+				.with_codepoints(collector.codepoints());
+			Font::create_with_options(&output, &options, &["Data/test-5x7.bdf"])?;
+
+			// € is missing from the font
+			let font = Font::load(&output)?;
+			let codepoints: Vec<u32> = font.glyphs.iter().map(|g| g.codepoint).collect();
+			assert_eq!(
+				vec![' ' as u32, 'A' as u32, 'B' as u32, 'g' as u32],
+				codepoints
+			);
+			assert_eq!(5, font.glyph('g' as u32).unwrap().width);
+
+			Ok(())
+		}
+
+		#[test]
+		fn kerning_is_added_once() -> anyhow::Result<()> {
+			use ab_glyph::Font as _;
+			use ab_glyph::ScaleFont as _;
+
+			let ttf = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+			if !std::path::Path::new(ttf).exists() {
+				eprintln!("Skipping kerning, {} not found", ttf);
+				return Ok(());
+			}
+			let (_test_dir, output) = temp_output("kerning");
+			let output = output.to_string_lossy().to_string();
+			let options = FontOptions::default()
+				.with_texsize(256)
+				.with_size(32)
+				.with_render_mode(RenderMode::Coverage)
+				.with_kerning(true)
+				.with_codepoints(vec!['A' as u32, 'V' as u32]);
+			Font::create_with_options(&output, &options, &[ttf])?;
+			let font = Font::load(&output)?;
+
+			// the scale is the size, with the default size mode
+			let data = std::fs::read(ttf)?;
+			let reference = ab_glyph::FontRef::try_from_slice(&data)?;
+			let scaled = reference.as_scaled(32.0);
+			let (a, v) = (reference.glyph_id('A'), reference.glyph_id('V'));
+			let kerning = scaled.kern(a, v);
+			assert!(kerning < 0.0);
+
+			// V starts after the advance of A, and the kerning of the pair, advances are whole pixels
+			let block = TextLayout::default().layout(&font, "AV");
+			let expected = scaled.h_advance(a) + kerning;
+			assert!(
+				(block.quads[1].x - expected).abs() < 1.0,
+				"V at {}, expected {}",
+				block.quads[1].x,
+				expected
+			);
+
+			Ok(())
+		}
+
+		#[test]
+		fn size_modes_work() -> anyhow::Result<()> {
+			let (_test_dir, output) = temp_output("size-mode");
+			let output = output.to_string_lossy().to_string();
+
+			// bitmap fonts always use their pixel size
+			let options = FontOptions::default()
+				.with_texsize(64)
+				.with_render_mode(RenderMode::Bitmap)
+				.with_size_mode(SizeMode::CapHeight);
+			Font::create_with_options(&output, &options, &["Data/test-5x7.bdf"])?;
+			let info = Font::info(&output, None)?;
+			assert_eq!((7, SizeMode::Scale), (info.version, info.size_mode));
+			assert_eq!(9.0, info.line_height);
+
+			let ttf = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+			if !std::path::Path::new(ttf).exists() {
+				eprintln!("Skipping outline size modes, {} not found", ttf);
+				return Ok(());
+			}
+			for size_mode in [SizeMode::Em, SizeMode::CapHeight, SizeMode::LineHeight] {
+				let options = FontOptions::default()
+					.with_texsize(256)
+					.with_size(32)
+					.with_size_mode(size_mode)
+					.with_render_mode(RenderMode::Coverage)
+					.with_codepoints(vec!['H' as u32]);
+				Font::create_with_options(&output, &options, &[ttf])?;
+
+				let info = Font::info(&output, None)?;
+				assert_eq!((7, size_mode), (info.version, info.size_mode));
+				assert_eq!(RenderMode::Coverage, info.render_mode);
+				let h = info.glyphs[0].height;
+				match size_mode {
+					SizeMode::Em => assert!((info.em_size - 32.0).abs() < 0.01),
+					SizeMode::CapHeight => assert_eq!(32, h),
+					SizeMode::LineHeight => assert!((info.line_height - 32.0).abs() < 0.01),
+					SizeMode::Scale => {},
+				}
+			}
+
+			Ok(())
+		}
+
+		// a collection of the given fonts, with the table offsets moved
+		fn font_collection(fonts: &[Vec<u8>]) -> anyhow::Result<Vec<u8>> {
+			let mut data = Vec::new();
+			data.write_all(b"ttcf")?;
+			data.write_u32::<BigEndian>(0x00010000)?;
+			data.write_u32::<BigEndian>(fonts.len() as u32)?;
+			let mut offset = 12 + 4 * fonts.len();
+			for font in fonts {
+				data.write_u32::<BigEndian>(offset as u32)?;
+				offset += font.len();
+			}
+			for font in fonts {
+				let start = data.len();
+				data.extend_from_slice(font);
+				let table_count = BigEndian::read_u16(&font[4..6]) as usize;
+				for t in 0..table_count {
+					let pos = start + 12 + 16 * t + 8;
+					let table_offset = BigEndian::read_u32(&data[pos..pos + 4]);
+					BigEndian::write_u32(&mut data[pos..pos + 4], table_offset + start as u32);
+				}
+			}
+			Ok(data)
+		}
+
+		#[test]
+		fn font_collection_face_index_works() -> anyhow::Result<()> {
+			let (test_dir, output) = temp_output("collection");
+			let output = output.to_string_lossy().to_string();
+
+			let dejavu = "/usr/share/fonts/truetype/dejavu";
+			let sans = format!("{}/DejaVuSans.ttf", dejavu);
+			let serif = format!("{}/DejaVuSerif.ttf", dejavu);
+			if !std::path::Path::new(&sans).exists() || !std::path::Path::new(&serif).exists() {
+				eprintln!("Skipping font collections, DejaVu fonts not found");
+				return Ok(());
+			}
+			let ttc = test_dir.join("collection.ttc");
+			std::fs::write(
+				&ttc,
+				font_collection(&[std::fs::read(&sans)?, std::fs::read(&serif)?])?,
+			)?;
+			let ttc = ttc.to_string_lossy().to_string();
+
+			let mut widths = Vec::new();
+			for (face_index, ttf) in [(0, &sans), (1, &serif)] {
+				let options = FontOptions::default()
+					.with_texsize(256)
+					.with_size(32)
+					.with_render_mode(RenderMode::Coverage)
+					.with_codepoints(vec!['H' as u32]);
+				Font::create_with_options(&output, &options, &[ttf.as_str()])?;
+				let expected = Font::info(&output, None)?.glyphs[0].width;
+
+				Font::create_with_options(
+					&output,
+					&options.with_face_index(face_index),
+					&[ttc.as_str()],
+				)?;
+				let width = Font::info(&output, None)?.glyphs[0].width;
+				assert_eq!(expected, width);
+				widths.push(width);
+			}
+			assert_ne!(widths[0], widths[1]);
+
+			// errors, not panics
+			let options = FontOptions::default().with_face_index(2);
+			assert!(Font::create_with_options(&output, &options, &[ttc.as_str()]).is_err());
+			let options = FontOptions::default()
+				.with_variations(FontOptions::parse_variations("wght=700").unwrap());
+			assert!(Font::create_with_options(&output, &options, &[sans.as_str()]).is_err());
+			std::fs::write(test_dir.join("broken.ttf"), b"ttcf")?;
+			let broken = test_dir.join("broken.ttf").to_string_lossy().to_string();
+			assert!(Font::create_with_options(
+				&output,
+				&FontOptions::default(),
+				&[broken.as_str()]
+			)
+			.is_err());
+
+			Ok(())
+		}
+
+		#[test]
+		fn parse_variations_works() {
+			assert_eq!(
+				Ok(vec![(*b"wght", 700.0), (*b"wdth", 90.5), (*b"ab  ", -1.0)]),
+				FontOptions::parse_variations("wght=700, wdth=90.5,ab=-1")
+			);
+			assert!(FontOptions::parse_variations("wght").is_err());
+			assert!(FontOptions::parse_variations("weight=700").is_err());
+			assert!(FontOptions::parse_variations("wght=bold").is_err());
+		}
+	}
+}
@@ -0,0 +1,1024 @@
+mod test {
+	#[allow(non_snake_case)]
+	mod Packer {
+		use std::io::{Read, Seek, SeekFrom};
+		use std::path::PathBuf;
+
+		use omt::name_map::NameMap;
+		use omt::packer::command_packer::CommandPacker;
+		use omt::packer::command_packer_apply::CommandPackerApply;
+		use omt::packer::command_packer_cat::CommandPackerCat;
+		use omt::packer::command_packer_diff::CommandPackerDiff;
+		use omt::packer::command_packer_list::CommandPackerList;
+		use omt::packer::command_packer_pack::CommandPackerPack;
+		use omt::packer::command_packer_stats::CommandPackerStats;
+		use omt::packer::command_packer_unpack::CommandPackerUnpack;
+		use omt::packer::command_packer_verify::CommandPackerVerify;
+		use omt::packer::AlignRule;
+		use omt::packer::ArchiveReader;
+		use omt::packer::ArchiveSet;
+		use omt::packer::ArchiveStats;
+		use omt::packer::Compression;
+		use omt::packer::EntryFilter;
+		use omt::packer::EntryType;
+		use omt::packer::Helper;
+		use omt::packer::NameHash;
+		use omt::packer::UnpackLayout;
+		use omt::util::CrcHelper;
+		use omt::util::FileHelper;
+
+		fn temp_output(name: &str) -> (PathBuf, PathBuf) {
+			let test_dir = std::env::temp_dir();
+			let test_dir = test_dir.join("omt-test");
+			let test_dir = test_dir.join("packer");
+
+			std::fs::create_dir_all(&test_dir).unwrap();
+			let output = test_dir.join(name);
+			eprintln!("{:?}", &output);
+			(test_dir, output)
+		}
+
+		// writes the files below name-data, and packs them in the given order
+		fn pack(name: &str, files: &[(&str, &[u8])]) -> anyhow::Result<PathBuf> {
+			pack_with(name, files, |_| {})
+		}
+
+		// a named set of pack options, for tests running over several configurations
+		type PackConfig = (&'static str, fn(&mut CommandPackerPack));
+
+		fn pack_with(
+			name: &str,
+			files: &[(&str, &[u8])],
+			configure: impl FnOnce(&mut CommandPackerPack),
+		) -> anyhow::Result<PathBuf> {
+			let (test_dir, output) = temp_output(name);
+			let basepath = test_dir.join(format!("{}-data", name));
+			let mut paklist = String::new();
+			for (filename, data) in files {
+				let path = basepath.join(filename);
+				std::fs::create_dir_all(path.parent().unwrap())?;
+				std::fs::write(&path, data)?;
+				paklist.push_str(filename);
+				paklist.push('\n');
+			}
+			let paklist_name = test_dir.join(format!("{}.paklist", name));
+			std::fs::write(&paklist_name, paklist)?;
+
+			let output = output.with_extension("omar");
+			let mut command = CommandPackerPack::new();
+			command.set_basepath(&basepath.to_string_lossy());
+			command.set_paklist(&paklist_name.to_string_lossy());
+			command.set_output(&output.to_string_lossy());
+			configure(&mut command);
+			command.run()?;
+			Ok(output)
+		}
+
+		#[test]
+		fn archive_reader_works() -> anyhow::Result<()> {
+			let output = pack(
+				"reader",
+				&[
+					("a.txt", b"first"),
+					("textures/Player.png", b"0123456789"),
+					("empty.bin", b""),
+				],
+			)?;
+
+			let reader = ArchiveReader::open(&output)?;
+			assert_eq!(3, reader.len());
+
+			// names are matched like when packing, case insensitive
+			let entry = *reader.find("textures/player.png").unwrap();
+			assert_eq!(CrcHelper::crc_from_name("textures/Player.png"), entry.crc());
+			assert_eq!(b"0123456789".to_vec(), reader.read(&entry)?);
+			assert!(reader.find("missing.txt").is_none());
+
+			let first = *reader
+				.find_by_crc(CrcHelper::crc_from_name("a.txt"))
+				.unwrap();
+			assert_eq!(b"first".to_vec(), reader.read(&first)?);
+			let empty = *reader.find("empty.bin").unwrap();
+			assert!(reader.read(&empty)?.is_empty());
+
+			// streaming, with seeks relative to the entry
+			let mut stream = reader.open_entry(&entry);
+			let mut buf = [0u8; 3];
+			stream.seek(SeekFrom::Start(4))?;
+			stream.read_exact(&mut buf)?;
+			assert_eq!(b"456", &buf);
+			stream.seek(SeekFrom::End(-2))?;
+			let mut rest = Vec::new();
+			stream.read_to_end(&mut rest)?;
+			assert_eq!(b"89".to_vec(), rest);
+			assert!(stream.seek(SeekFrom::Current(-100)).is_err());
+
+			// a second reader doesn't disturb the first
+			let mut other = reader.open_entry(&first);
+			stream.seek(SeekFrom::Start(0))?;
+			other.read_exact(&mut buf)?;
+			let mut buf2 = [0u8; 3];
+			stream.read_exact(&mut buf2)?;
+			assert_eq!((b"fir", b"012"), (&buf, &buf2));
+
+			Ok(())
+		}
+
+		#[test]
+		fn compressed_archive_works() -> anyhow::Result<()> {
+			let text = "All work and no play makes Jack a dull boy.\n".repeat(200);
+			// xorshift, doesn't shrink
+			let mut state = 0x2545f491u32;
+			let noise: Vec<u8> = (0..4096)
+				.map(|_| {
+					state ^= state << 13;
+					state ^= state >> 17;
+					state ^= state << 5;
+					state as u8
+				})
+				.collect();
+
+			let output = pack_with(
+				"compressed",
+				&[
+					("text.txt", text.as_bytes()),
+					("noise.bin", &noise),
+					("empty.bin", b""),
+				],
+				|command| command.set_compress(true),
+			)?;
+
+			let data = std::fs::read(&output)?;
+			assert_eq!(3, data[4]);
+
+			let reader = ArchiveReader::open(&output)?;
+			let entry = *reader.find("text.txt").unwrap();
+			assert_ne!(Compression::Stored, entry.compression());
+			assert!(entry.stored_size() < entry.size());
+			assert_eq!(text.len() as u64, entry.size());
+			assert_eq!(text.as_bytes().to_vec(), reader.read(&entry)?);
+
+			let noise_entry = *reader.find("noise.bin").unwrap();
+			assert_eq!(Compression::Stored, noise_entry.compression());
+			assert_eq!(noise_entry.size(), noise_entry.stored_size());
+			assert_eq!(noise, reader.read(&noise_entry)?);
+
+			let empty = *reader.find("empty.bin").unwrap();
+			assert!(reader.read(&empty)?.is_empty());
+
+			// streaming works on the uncompressed data
+			let mut stream = reader.open_entry(&entry);
+			stream.seek(SeekFrom::Start(4))?;
+			let mut buf = [0u8; 4];
+			stream.read_exact(&mut buf)?;
+			assert_eq!(b"work", &buf);
+
+			Ok(())
+		}
+
+		#[test]
+		fn embedded_names_work() -> anyhow::Result<()> {
+			let files: &[(&str, &[u8])] = &[("a.txt", b"first"), ("textures/Player.png", b"0123")];
+
+			let stripped = pack("names-stripped", files)?;
+			let reader = ArchiveReader::open(&stripped)?;
+			assert!(!reader.has_names());
+			assert_eq!(None, reader.name(&reader.entries()[0]));
+
+			for compress in [false, true] {
+				let output = pack_with(&format!("names-{}", compress), files, |command| {
+					command.set_embed_names(true);
+					command.set_compress(compress);
+				})?;
+				let reader = ArchiveReader::open(&output)?;
+				assert!(reader.has_names());
+				let entry = *reader.find("textures/player.png").unwrap();
+				assert_eq!(Some("textures/Player.png"), reader.name(&entry));
+				assert_eq!(b"0123".to_vec(), reader.read(&entry)?);
+				let first = *reader.find("a.txt").unwrap();
+				assert_eq!(Some("a.txt"), reader.name(&first));
+
+				// unpack uses the names without a name map
+				let (test_dir, _) = temp_output("names");
+				let targetpath = test_dir.join(format!("names-{}-unpacked", compress));
+				let _ = std::fs::remove_dir_all(&targetpath);
+				std::fs::create_dir_all(&targetpath)?;
+				let mut command = CommandPackerUnpack::new();
+				command.set_input(&output.to_string_lossy());
+				command.set_targetpath(&targetpath.to_string_lossy());
+				command.set_names_only(true);
+				command.run()?;
+				assert_eq!(
+					b"0123".to_vec(),
+					std::fs::read(targetpath.join("textures/Player.png"))?
+				);
+			}
+
+			Ok(())
+		}
+
+		#[test]
+		fn unpack_layouts_reject_unsafe_names() -> anyhow::Result<()> {
+			assert!(FileHelper::safe_relative_path("textures/a.png").is_ok());
+			for name in ["", "/etc/passwd", "\\x", "../a", "a/../../b", "a//b", "./a"] {
+				assert!(FileHelper::safe_relative_path(name).is_err(), "{:?}", name);
+			}
+			// drives, and streams only exist on windows
+			for name in ["c:/a", "a:stream"] {
+				let result = FileHelper::safe_relative_path(name);
+				assert_eq!(cfg!(windows), result.is_err(), "{:?}", name);
+			}
+
+			let output = pack(
+				"unpack-layouts",
+				&[("a.txt", b"a"), ("textures/b.png", b"b"), ("c.txt", b"c")],
+			)?;
+			// names from a name map can point anywhere
+			let (test_dir, name_map) = temp_output("unpack-layouts.omna");
+			let _ = std::fs::remove_file(&name_map);
+			let mut nm = NameMap::load_or_create(&name_map.to_string_lossy())?;
+			nm.insert(CrcHelper::crc_from_name("a.txt"), "a.txt".to_string());
+			nm.insert(
+				CrcHelper::crc_from_name("textures/b.png"),
+				"textures/b.png".to_string(),
+			);
+			nm.insert(CrcHelper::crc_from_name("c.txt"), "../c.txt".to_string());
+			nm.save(&name_map.to_string_lossy())?;
+
+			let unpack = |layout: UnpackLayout| {
+				let targetpath = test_dir.join(format!("unpack-layouts-{}", layout));
+				let _ = std::fs::remove_dir_all(&targetpath);
+				std::fs::create_dir_all(&targetpath)?;
+				let mut command = CommandPackerUnpack::new();
+				command.set_input(&output.to_string_lossy());
+				command.set_name_map(&name_map.to_string_lossy());
+				command.set_targetpath(&targetpath.to_string_lossy());
+				command.set_layout(layout);
+				let result = command.run();
+				anyhow::Ok((targetpath, result))
+			};
+
+			let (targetpath, result) = unpack(UnpackLayout::Crc)?;
+			assert_eq!(3, result?);
+			let crc_name = format!("{:#010X}", CrcHelper::crc_from_name("c.txt"));
+			assert_eq!(b"c".to_vec(), std::fs::read(targetpath.join(crc_name))?);
+
+			// the traversal is skipped, and reported, the rest is unpacked
+			let (targetpath, result) = unpack(UnpackLayout::Tree)?;
+			assert!(result.is_err());
+			assert_eq!(
+				b"b".to_vec(),
+				std::fs::read(targetpath.join("textures/b.png"))?
+			);
+			assert!(!test_dir.join("c.txt").exists());
+
+			// without separators the name stays inside
+			let (targetpath, result) = unpack(UnpackLayout::Flat)?;
+			assert_eq!(3, result?);
+			assert_eq!(
+				b"b".to_vec(),
+				std::fs::read(targetpath.join("textures_b.png"))?
+			);
+			assert_eq!(b"c".to_vec(), std::fs::read(targetpath.join(".._c.txt"))?);
+
+			Ok(())
+		}
+
+		#[test]
+		fn unpack_filters_and_cat_work() -> anyhow::Result<()> {
+			let files: &[(&str, &[u8])] = &[
+				("a.txt", b"a"),
+				("textures/b.png", b"bbbb"),
+				("textures/c.png", b"cccccccc"),
+			];
+			let output = pack_with("filters", files, |command| command.set_embed_names(true))?;
+			let (test_dir, _) = temp_output("filters");
+
+			let unpack = |filter: EntryFilter| {
+				let targetpath = test_dir.join("filters-unpacked");
+				let _ = std::fs::remove_dir_all(&targetpath);
+				std::fs::create_dir_all(&targetpath)?;
+				let mut command = CommandPackerUnpack::new();
+				command.set_input(&output.to_string_lossy());
+				command.set_targetpath(&targetpath.to_string_lossy());
+				command.set_layout(UnpackLayout::Tree);
+				command.set_filter(filter);
+				command.run()
+			};
+
+			assert_eq!(3, unpack(EntryFilter::new())?);
+			let mut filter = EntryFilter::new();
+			filter.add_name("textures/*")?;
+			assert_eq!(2, unpack(filter.clone())?);
+			assert_eq!(
+				b"bbbb".to_vec(),
+				std::fs::read(test_dir.join("filters-unpacked/textures/b.png"))?
+			);
+			assert!(!test_dir.join("filters-unpacked/a.txt").exists());
+			// globs, or crcs, within the size range
+			filter.add_crc(CrcHelper::crc_from_name("a.txt"));
+			assert_eq!(3, unpack(filter.clone())?);
+			filter.set_min_size(Some(2));
+			filter.set_max_size(Some(4));
+			assert_eq!(1, unpack(filter)?);
+
+			// name filters need names
+			let unnamed = pack("filters-unnamed", files)?;
+			let mut command = CommandPackerUnpack::new();
+			command.set_input(&unnamed.to_string_lossy());
+			let mut filter = EntryFilter::new();
+			filter.add_name("*.txt")?;
+			command.set_filter(filter);
+			assert!(command.run().is_err());
+
+			// cat finds entries by name without embedded names, or by crc
+			let mut command = CommandPackerCat::new();
+			command.set_input(&unnamed.to_string_lossy());
+			command.set_name("textures/c.png");
+			let mut data = Vec::new();
+			assert_eq!(8, command.cat(&mut data)?);
+			assert_eq!(b"cccccccc".to_vec(), data);
+
+			let mut command = CommandPackerCat::new();
+			command.set_input(&unnamed.to_string_lossy());
+			let crc = format!("{:#010x}", CrcHelper::crc_from_name("a.txt"));
+			command.set_crc(EntryFilter::parse_crc(&crc).unwrap());
+			let mut data = Vec::new();
+			command.cat(&mut data)?;
+			assert_eq!(b"a".to_vec(), data);
+
+			command.set_name("missing.txt");
+			assert!(command.cat(&mut Vec::new()).is_err());
+
+			Ok(())
+		}
+
+		#[test]
+		fn stats_sniff_entry_types() -> anyhow::Result<()> {
+			let files: &[(&str, &[u8])] = &[
+				("atlases/ui.omatlas", b"SO\x01\x00OMATLASS\x01\x00\x00\x00"),
+				("fonts/big.omfont", b"OMFONT\x05\x00\x00\x00"),
+				("sounds/sfx.omsndbn", b"SO\x01\x00OMSNDBNK\x02\x00\x00\x00"),
+				("names.omna", b"OMNA\x01\x00\x00\x00"),
+				("textures/a.png", b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"),
+				(
+					"textures/b.png",
+					b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x20",
+				),
+				("music/theme.ogg", b"OggS\x00\x02"),
+				("sounds/jump.wav", b"RIFF\x24\x00\x00\x00WAVEfmt "),
+				("scripts/main.lua", b"print( 'hello' )\n"),
+				("scripts/compiled.luac", b"\x1bLuaT\x00"),
+				("readme.txt", b"hello"),
+				("data.bin", &[0u8; 64]),
+			];
+			let output = pack_with("stats", files, |command| {
+				command.set_embed_names(true);
+				command.set_compress(true);
+			})?;
+
+			let reader = ArchiveReader::open(&output)?;
+			let stats = ArchiveStats::collect(&reader, None, 3)?;
+			assert_eq!(files.len(), stats.entries);
+			let type_of = |name: &str| {
+				let entry = reader.find(name).unwrap();
+				let mut data = Vec::new();
+				reader.open_entry(entry).read_to_end(&mut data).unwrap();
+				EntryType::sniff(&data, reader.name(entry))
+			};
+			for (name, expected) in [
+				("atlases/ui.omatlas", EntryType::Atlas),
+				("fonts/big.omfont", EntryType::Font),
+				("sounds/sfx.omsndbn", EntryType::Soundbank),
+				("names.omna", EntryType::NameMap),
+				("textures/a.png", EntryType::Png),
+				("music/theme.ogg", EntryType::Ogg),
+				("sounds/jump.wav", EntryType::Wav),
+				("scripts/main.lua", EntryType::Lua),
+				("scripts/compiled.luac", EntryType::Lua),
+				("readme.txt", EntryType::Text),
+				("data.bin", EntryType::Unknown),
+			] {
+				assert_eq!(expected, type_of(name), "{}", name);
+			}
+
+			// largest first
+			assert_eq!("unknown", stats.types[0].name);
+			let png = stats.types.iter().find(|t| t.name == "png").unwrap();
+			assert_eq!((2, 36), (png.entries, png.size));
+			let sounds = stats
+				.directories
+				.iter()
+				.find(|d| d.name == "sounds")
+				.unwrap();
+			assert_eq!(2, sounds.entries);
+			assert!(stats.directories.iter().any(|d| d.name == "."));
+			assert_eq!(3, stats.largest.len());
+			assert_eq!(Some("data.bin"), stats.largest[0].name.as_deref());
+			// zeros compress well
+			assert!(stats.largest[0].stored_size < 64);
+
+			let json = serde_json::to_value(&stats)?;
+			assert_eq!("unknown", json["largest"][0]["entry_type"]);
+			assert_eq!(files.len(), json["entries"].as_u64().unwrap() as usize);
+
+			let mut command = CommandPackerStats::new();
+			command.set_input(&output.to_string_lossy());
+			command.set_json(true);
+			assert_eq!(files.len() as u32, command.run()?);
+
+			// only a prefix of compressed entries is decompressed, long matches included
+			let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+			data.extend_from_slice(&[b'a'; 1000]);
+			for i in 0..20_000u64 {
+				let line = format!("line {}\n", i.wrapping_mul(2654435761) % 1000003);
+				data.extend_from_slice(line.as_bytes());
+			}
+			for compression in [Compression::Deflate, Compression::Lz4] {
+				let compressed = compression.compress(&data)?;
+				let mut stored = compressed.as_slice();
+				let prefix = compression.decompress_prefix(&mut stored, data.len(), 512)?;
+				assert_eq!(&data[..512], &prefix[..], "{}", compression);
+				assert!(!stored.is_empty(), "{}", compression);
+				let all =
+					compression.decompress_prefix(compressed.as_slice(), data.len(), usize::MAX)?;
+				assert_eq!(data, all, "{}", compression);
+			}
+			let short = Compression::Lz4.compress(b"abcabcabcabc")?;
+			assert_eq!(
+				b"abcab",
+				&Compression::Lz4.decompress_prefix(short.as_slice(), 12, 5)?[..]
+			);
+			assert!(Compression::Lz4
+				.decompress_prefix(&short[..short.len() - 1], 12, 12)
+				.is_err());
+
+			Ok(())
+		}
+
+		fn verify(output: &std::path::Path) -> anyhow::Result<u32> {
+			let mut command = CommandPackerVerify::new();
+			command.set_input(&output.to_string_lossy());
+			command.run()
+		}
+
+		#[test]
+		fn verify_works() -> anyhow::Result<()> {
+			let files: &[(&str, &[u8])] = &[("a.txt", b"first"), ("b.txt", b"second")];
+
+			let output = pack_with("verify", files, |command| command.set_checksums(true))?;
+			let data = std::fs::read(&output)?;
+			assert_eq!(4, data[4]);
+			let reader = ArchiveReader::open(&output)?;
+			let entry = *reader.find("b.txt").unwrap();
+			assert_eq!(Some(CrcHelper::crc_from_data(b"second")), entry.checksum());
+			assert_eq!(2, verify(&output)?);
+
+			// flipped payload byte
+			let broken = output.with_extension("broken");
+			let mut corrupted = data.clone();
+			*corrupted.last_mut().unwrap() ^= 0xff;
+			std::fs::write(&broken, &corrupted)?;
+			assert!(verify(&broken).is_err());
+
+			// without checksums only the structure is checked
+			let stripped = pack("verify-stripped", files)?;
+			assert_eq!(2, verify(&stripped)?);
+			let data = std::fs::read(&stripped)?;
+
+			// the second entry points to the data of the first, v2 directory starts at 12
+			let mut overlapping = data.clone();
+			overlapping[12 + 12 + 4..12 + 12 + 8].copy_from_slice(&0u32.to_le_bytes());
+			std::fs::write(&broken, &overlapping)?;
+			assert!(verify(&broken).is_err());
+
+			let mut duplicated = data.clone();
+			duplicated.copy_within(12..16, 12 + 12);
+			std::fs::write(&broken, &duplicated)?;
+			assert!(verify(&broken).is_err());
+
+			Ok(())
+		}
+
+		#[test]
+		fn name_collisions_are_detected() -> anyhow::Result<()> {
+			// same crc32 for different names
+			let colliding: &[(&str, &[u8])] =
+				&[("f29685295.bin", b"first"), ("f32060020.bin", b"second")];
+			assert_eq!(
+				CrcHelper::crc_from_name("f29685295.bin"),
+				CrcHelper::crc_from_name("f32060020.bin")
+			);
+			assert!(pack("collision", colliding).is_err());
+
+			// names are case insensitive, and non ascii characters become spaces
+			assert!(pack("duplicate", &[("a.txt", b"first"), ("A.txt", b"second")]).is_err());
+			assert!(pack(
+				"duplicate-clean",
+				&[("ä.txt", b"first"), ("ö.txt", b"second")]
+			)
+			.is_err());
+
+			for (name_hash, salt) in [
+				(NameHash::Crc32, 0x5eed),
+				(NameHash::Crc32c, 0),
+				(NameHash::Fnv1a, 0),
+				(NameHash::Fnv1a, 0x5eed),
+			] {
+				let output =
+					pack_with(&format!("collision-{}", name_hash), colliding, |command| {
+						command.set_name_hash(name_hash, salt);
+						command.set_embed_names(true);
+					})?;
+				let data = std::fs::read(&output)?;
+				assert_eq!(5, data[4]);
+
+				let reader = ArchiveReader::open(&output)?;
+				assert_eq!((name_hash, salt), (reader.name_hash(), reader.salt()));
+				let entry = *reader.find("F32060020.bin").unwrap();
+				assert_eq!(name_hash.crc_from_name("f32060020.bin", salt), entry.crc());
+				assert_eq!(b"second".to_vec(), reader.read(&entry)?);
+				assert_eq!(Some("f32060020.bin"), reader.name(&entry));
+				assert_eq!(
+					b"first".to_vec(),
+					reader.read(reader.find("f29685295.bin").unwrap())?
+				);
+				assert_eq!(2, verify(&output)?);
+			}
+
+			// reference value for fnv-1a
+			assert_eq!(0xe40c292c, NameHash::Fnv1a.crc_from_name("a", 0));
+
+			// no salt, and crc32 is the plain name crc
+			assert_eq!(
+				CrcHelper::crc_from_name("Textures/Player.png"),
+				NameHash::Crc32.crc_from_name("Textures/Player.png", 0)
+			);
+
+			Ok(())
+		}
+
+		#[test]
+		fn pack_streams_and_fails_for_missing_inputs() -> anyhow::Result<()> {
+			// larger than any copy buffer
+			let large: Vec<u8> = (0..3 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+			let output = pack_with(
+				"streaming",
+				&[("large.bin", &large), ("small.txt", b"small")],
+				|command| command.set_checksums(true),
+			)?;
+			let reader = ArchiveReader::open(&output)?;
+			let entry = *reader.find("large.bin").unwrap();
+			assert_eq!(large.len() as u64, entry.size());
+			assert_eq!(Some(CrcHelper::crc_from_data(&large)), entry.checksum());
+			assert_eq!(
+				b"small".to_vec(),
+				reader.read(reader.find("small.txt").unwrap())?
+			);
+			assert_eq!(2, verify(&output)?);
+
+			// a paklist with a file that doesn't exist
+			let (test_dir, _) = temp_output("missing");
+			let paklist = test_dir.join("missing.paklist");
+			std::fs::write(&paklist, "a.txt\nmissing.txt\n")?;
+			let basepath = test_dir.join("missing-data");
+			std::fs::create_dir_all(&basepath)?;
+			std::fs::write(basepath.join("a.txt"), b"first")?;
+			let mut command = CommandPackerPack::new();
+			command.set_basepath(&basepath.to_string_lossy());
+			command.set_paklist(&paklist.to_string_lossy());
+			command.set_output(&test_dir.join("missing.omar").to_string_lossy());
+			let e = command.run().unwrap_err();
+			assert!(e.to_string().contains("1 problem(s)"), "{}", e);
+
+			// and a missing paklist
+			command.set_paklist(&test_dir.join("missing-too.paklist").to_string_lossy());
+			assert!(command.run().is_err());
+
+			Ok(())
+		}
+
+		#[test]
+		fn aligned_archive_works() -> anyhow::Result<()> {
+			let rules: Vec<AlignRule> = vec!["16".parse().unwrap(), "*.ktx=4096".parse().unwrap()];
+			assert_eq!(16, AlignRule::align_for(&rules, "a.txt"));
+			assert_eq!(4096, AlignRule::align_for(&rules, "textures/player.ktx"));
+			assert!("12".parse::<AlignRule>().is_err());
+			assert!("*.ktx=".parse::<AlignRule>().is_err());
+
+			let files: &[(&str, &[u8])] = &[
+				("a.txt", b"first"),
+				("textures/player.ktx", b"0123456789"),
+				("b.txt", b"second"),
+			];
+			for compress in [false, true] {
+				let output = pack_with(&format!("aligned-{}", compress), files, |command| {
+					command.set_align_rules(&rules);
+					command.set_compress(compress);
+				})?;
+				let data = std::fs::read(&output)?;
+				assert_eq!(6, data[4]);
+
+				let reader = ArchiveReader::open(&output)?;
+				assert_eq!(16, reader.alignment());
+				let texture = *reader.find("textures/player.ktx").unwrap();
+				assert_eq!(0, texture.offset() % 4096);
+				assert_eq!(b"0123456789".to_vec(), reader.read(&texture)?);
+				for (name, content) in [("a.txt", b"first".as_slice()), ("b.txt", b"second")] {
+					let entry = *reader.find(name).unwrap();
+					assert_eq!(0, entry.offset() % 16);
+					assert_eq!(content.to_vec(), reader.read(&entry)?);
+				}
+				assert_eq!(3, verify(&output)?);
+			}
+
+			// the header has the alignment all entries share
+			let rules: Vec<AlignRule> = vec!["16".parse().unwrap(), "*.txt=1".parse().unwrap()];
+			let output = pack_with("aligned-mixed", files, |command| {
+				command.set_align_rules(&rules)
+			})?;
+			let reader = ArchiveReader::open(&output)?;
+			assert_eq!(1, reader.alignment());
+			assert_eq!(0, reader.find("textures/player.ktx").unwrap().offset() % 16);
+
+			Ok(())
+		}
+
+		#[test]
+		fn diff_and_apply_work() -> anyhow::Result<()> {
+			let configs: [PackConfig; 5] = [
+				("plain", |_| {}),
+				("compress", |command| command.set_compress(true)),
+				("full", |command| {
+					command.set_compress(true);
+					command.set_checksums(true);
+					command.set_embed_names(true);
+				}),
+				("reproducible", |command| {
+					command.set_compress(true);
+					command.set_embed_names(true);
+					command.set_reproducible(true);
+				}),
+				("aligned", |command| {
+					command.set_embed_names(true);
+					command.set_align_rules(&["*.bin=64".parse().unwrap()]);
+				}),
+			];
+			for (name, configure) in configs {
+				let old = pack_with(
+					&format!("diff-old-{}", name),
+					&[
+						("a.txt", b"same"),
+						("zeros.bin", &[0u8; 1024]),
+						("b.txt", b"old"),
+						("c.txt", b"removed"),
+					],
+					configure,
+				)?;
+				// a different order, the changed, and added entries don't shrink
+				let new = pack_with(
+					&format!("diff-new-{}", name),
+					&[
+						("d.txt", b"added"),
+						("b.txt", b"new!"),
+						("a.txt", b"same"),
+						("zeros.bin", &[0u8; 1024]),
+					],
+					configure,
+				)?;
+
+				let patch = old.with_extension("patch.omar");
+				let mut command = CommandPackerDiff::new();
+				command.set_old(&old.to_string_lossy());
+				command.set_new(&new.to_string_lossy());
+				command.set_output(&patch.to_string_lossy());
+				command.run()?;
+
+				let reader = ArchiveReader::open(&patch)?;
+				let mut crcs: Vec<_> = reader.entries().iter().map(|e| e.crc()).collect();
+				crcs.sort();
+				let mut expected = vec![
+					CrcHelper::crc_from_name("b.txt"),
+					CrcHelper::crc_from_name("d.txt"),
+				];
+				expected.sort();
+				assert_eq!(expected, crcs, "{}", name);
+				assert_eq!(
+					Some([CrcHelper::crc_from_name("c.txt")].as_slice()),
+					reader.tombstones()
+				);
+
+				let applied = old.with_extension("applied.omar");
+				let mut command = CommandPackerApply::new();
+				command.set_input(&old.to_string_lossy());
+				command.set_patch(&patch.to_string_lossy());
+				command.set_output(&applied.to_string_lossy());
+				assert_eq!(4, command.run()?);
+
+				// rebuilt byte for byte
+				assert!(
+					std::fs::read(&applied)? == std::fs::read(&new)?,
+					"{} differs from {}",
+					applied.display(),
+					new.display()
+				);
+				assert_eq!(4, verify(&applied)?);
+
+				// the patch only fits the old archive
+				command.set_input(&new.to_string_lossy());
+				assert!(command.run().is_err());
+				// and normal archives are no patches
+				command.set_input(&old.to_string_lossy());
+				command.set_patch(&new.to_string_lossy());
+				assert!(command.run().is_err());
+			}
+
+			Ok(())
+		}
+
+		#[test]
+		fn reproducible_archives_are_identical() -> anyhow::Result<()> {
+			let mut files: Vec<(&str, &[u8])> = vec![
+				("a.txt", b"first"),
+				("b.txt", b"second"),
+				("textures/c.png", b"0123456789"),
+				("zeros.bin", &[0u8; 4096]),
+			];
+			let mut outputs = Vec::new();
+			for name in ["reproducible-1", "reproducible-2"] {
+				let (_, name_map) = temp_output(&format!("{}.omna", name));
+				let _ = std::fs::remove_file(&name_map);
+				let output = pack_with(name, &files, |command| {
+					command.set_reproducible(true);
+					command.set_compress(true);
+					command.set_embed_names(true);
+					command.set_checksums(true);
+					command.set_name_map(&name_map.to_string_lossy());
+				})?;
+				outputs.push((std::fs::read(output)?, std::fs::read(name_map)?));
+				files.reverse();
+			}
+			assert_eq!(outputs[0], outputs[1]);
+
+			// sorted by name
+			let output = pack_with("sorted-by-name", &files, |command| {
+				command.set_entry_order("name".parse().unwrap())
+			})?;
+			let reader = ArchiveReader::open(&output)?;
+			let crcs: Vec<u32> = reader.entries().iter().map(|e| e.crc()).collect();
+			let mut names: Vec<&str> = files.iter().map(|(name, _)| *name).collect();
+			names.sort();
+			let expected: Vec<u32> = names.iter().map(|n| CrcHelper::crc_from_name(n)).collect();
+			assert_eq!(expected, crcs);
+
+			Ok(())
+		}
+
+		#[test]
+		fn paklists_and_from_dir_work() -> anyhow::Result<()> {
+			// writes the files, the archive itself is not needed
+			let files: &[(&str, &[u8])] = &[
+				("a.txt", b"a"),
+				("b.tmp", b"b"),
+				("fonts/big.omfont", b"big"),
+				("fonts/small.omfont", b"small"),
+				("fonts/cache/old.omfont", b"old"),
+				("textures/a.png", b"png"),
+			];
+			pack("paklist", files)?;
+			let (test_dir, output) = temp_output("paklist-packed.omar");
+			let basepath = test_dir.join("paklist-data");
+
+			let packed_names = |configure: &dyn Fn(&mut CommandPackerPack)| {
+				let mut command = CommandPackerPack::new();
+				command.set_basepath(&basepath.to_string_lossy());
+				command.set_output(&output.to_string_lossy());
+				command.set_embed_names(true);
+				configure(&mut command);
+				command.run()?;
+				let reader = ArchiveReader::open(&output)?;
+				let mut names: Vec<String> = reader
+					.entries()
+					.iter()
+					.map(|e| reader.name(e).unwrap().to_string())
+					.collect();
+				names.sort();
+				anyhow::Ok(names)
+			};
+
+			let common = test_dir.join("paklist-common.paklist");
+			std::fs::write(&common, "# shared\ntextures/*.png\n")?;
+			let paklist = test_dir.join("paklist-main.paklist");
+			std::fs::write(
+				&paklist,
+				"# fonts, without the cache\n\n**/*.omfont\n!fonts/cache/*\na.txt\n@include paklist-common.paklist\n",
+			)?;
+			let names = packed_names(&|command| command.set_paklist(&paklist.to_string_lossy()))?;
+			assert_eq!(
+				vec![
+					"a.txt",
+					"fonts/big.omfont",
+					"fonts/small.omfont",
+					"textures/a.png"
+				],
+				names
+			);
+
+			// the deprecated helper reads paklists, too
+			let plain = test_dir.join("paklist-plain.paklist");
+			std::fs::write(&plain, "# plain names\na.txt\nb.tmp\n")?;
+			#[allow(deprecated)]
+			let filenames = Helper::filenames_in_file(&plain.to_string_lossy().to_string());
+			assert_eq!(
+				Ok(vec!["a.txt".to_string(), "b.tmp".to_string()]),
+				filenames
+			);
+
+			// includes can not loop
+			std::fs::write(&common, "@include paklist-main.paklist\n")?;
+			assert!(
+				packed_names(&|command| command.set_paklist(&paklist.to_string_lossy())).is_err()
+			);
+
+			std::fs::write(basepath.join(".omtignore"), "# temporary\n*.tmp\ncache/\n")?;
+			let names = packed_names(&|command| command.set_from_dir(true))?;
+			assert_eq!(
+				vec![
+					"a.txt",
+					"fonts/big.omfont",
+					"fonts/small.omfont",
+					"textures/a.png"
+				],
+				names
+			);
+
+			std::fs::write(basepath.join(".omtignore"), "*.tmp\n/fonts/\n!b.tmp\n")?;
+			let names = packed_names(&|command| command.set_from_dir(true))?;
+			assert_eq!(vec!["a.txt", "b.tmp", "textures/a.png"], names);
+
+			Ok(())
+		}
+
+		#[test]
+		fn archive_set_overlays_archives() -> anyhow::Result<()> {
+			let configure = |command: &mut CommandPackerPack| command.set_embed_names(true);
+			let base = pack_with(
+				"overlay-base",
+				&[("a.txt", b"base"), ("b.txt", b"base"), ("c.txt", b"base")],
+				configure,
+			)?;
+			let dlc = pack_with(
+				"overlay-dlc",
+				&[("b.txt", b"dlc"), ("d.txt", b"dlc")],
+				configure,
+			)?;
+			// a patch replacing a.txt, and removing c.txt, and e.txt which is in none of the archives
+			let old = pack_with(
+				"overlay-old",
+				&[("c.txt", b"base"), ("e.txt", b"old")],
+				configure,
+			)?;
+			let new = pack_with("overlay-new", &[("a.txt", b"patch")], configure)?;
+			let patch = old.with_extension("patch.omar");
+			let mut command = CommandPackerDiff::new();
+			command.set_old(&old.to_string_lossy());
+			command.set_new(&new.to_string_lossy());
+			command.set_output(&patch.to_string_lossy());
+			command.run()?;
+
+			let mut set = ArchiveSet::new();
+			for archive in [&base, &dlc, &patch] {
+				set.mount(archive)?;
+			}
+			assert_eq!(Some(b"patch".to_vec()), set.read_by_name("a.txt")?);
+			assert_eq!(Some(b"dlc".to_vec()), set.read_by_name("b.txt")?);
+			assert_eq!(None, set.read_by_name("c.txt")?);
+			assert_eq!(Some(b"dlc".to_vec()), set.read_by_name("d.txt")?);
+			assert!(set.find("e.txt").is_none());
+
+			let overlay = set.overlay();
+			let by_name = |name: &str| {
+				overlay
+					.iter()
+					.find(|o| o.crc() == CrcHelper::crc_from_name(name))
+					.map(|o| (o.archive(), o.shadowed().to_vec()))
+					.unwrap()
+			};
+			assert_eq!(4, overlay.len());
+			assert_eq!((Some(2), vec![0]), by_name("a.txt"));
+			assert_eq!((Some(1), vec![0]), by_name("b.txt"));
+			assert_eq!((None, vec![0]), by_name("c.txt"));
+			assert_eq!((Some(1), vec![]), by_name("d.txt"));
+			assert!(!overlay
+				.iter()
+				.any(|o| o.crc() == CrcHelper::crc_from_name("e.txt")));
+			assert_eq!(Some("c.txt"), set.name(CrcHelper::crc_from_name("c.txt")));
+
+			let mut command = CommandPackerList::new();
+			command.set_overlay(&[base, dlc, patch].map(|p| p.to_string_lossy().to_string()));
+			assert_eq!(3, command.run()?);
+
+			// crcs of different name hashes can not be compared
+			let fnv = pack_with("overlay-fnv", &[("a.txt", b"fnv")], |command| {
+				command.set_name_hash(NameHash::Fnv1a, 0)
+			})?;
+			assert!(set.mount(&fnv).is_err());
+
+			Ok(())
+		}
+
+		#[test]
+		fn archive_reader_reads_from_threads() -> anyhow::Result<()> {
+			let a = vec![b'a'; 64 * 1024];
+			let b = vec![b'b'; 64 * 1024];
+			let output = pack("reader-threads", &[("a.bin", &a), ("b.bin", &b)])?;
+
+			let reader = ArchiveReader::open(&output)?;
+			std::thread::scope(|scope| {
+				for (name, expected) in [("a.bin", &a), ("b.bin", &b)] {
+					let reader = &reader;
+					scope.spawn(move || {
+						let entry = reader.find(name).unwrap();
+						for _ in 0..50 {
+							// small reads, to interleave with the other thread
+							let mut data = Vec::new();
+							let mut buf = [0u8; 100];
+							let mut entry_reader = reader.open_entry(entry);
+							loop {
+								match entry_reader.read(&mut buf).unwrap() {
+									0 => break,
+									n => data.extend_from_slice(&buf[..n]),
+								}
+							}
+							assert!(&data == expected, "{} read wrong bytes", name);
+						}
+					});
+				}
+			});
+
+			Ok(())
+		}
+
+		#[cfg(feature = "mmap")]
+		#[test]
+		fn archive_reader_mmap_works() -> anyhow::Result<()> {
+			let output = pack("reader-mmap", &[("a.txt", b"first"), ("b.txt", b"second")])?;
+
+			let reader = ArchiveReader::open_mmap(&output)?;
+			let entry = *reader.find("b.txt").unwrap();
+			assert_eq!(b"second".to_vec(), reader.read(&entry)?);
+
+			Ok(())
+		}
+
+		#[test]
+		fn archive_reader_rejects_broken_archives() -> anyhow::Result<()> {
+			let output = pack("reader-broken", &[("a.txt", b"0123456789")])?;
+
+			let data = std::fs::read(&output)?;
+			let truncated = output.with_extension("truncated");
+			std::fs::write(&truncated, &data[..data.len() - 1])?;
+			assert!(ArchiveReader::open(&truncated).is_err());
+
+			let mut wrong_magic = data.clone();
+			wrong_magic[0] = b'X';
+			std::fs::write(&truncated, &wrong_magic)?;
+			assert!(ArchiveReader::open(&truncated).is_err());
+
+			assert!(ArchiveReader::open(output.with_extension("missing")).is_err());
+
+			// v7 with a single 4 byte entry, sizes claiming far more than the archive holds
+			let crafted = |size: u64, compression: u8| {
+				let mut data = b"OMAR\x07\x00\x00\x00".to_vec();
+				data.extend_from_slice(&1u32.to_le_bytes());
+				data.extend_from_slice(&[0u8; 8]); // salt, name hash, alignment, reserved
+				data.extend_from_slice(&CrcHelper::crc_from_name("a.txt").to_le_bytes());
+				data.extend_from_slice(&0u64.to_le_bytes());
+				data.extend_from_slice(&4u64.to_le_bytes());
+				data.extend_from_slice(&size.to_le_bytes());
+				data.extend_from_slice(&[compression, 0, 0, 0]);
+				data.extend_from_slice(&CrcHelper::crc_from_data(b"0123").to_le_bytes());
+				data.extend_from_slice(b"0123");
+				data
+			};
+			std::fs::write(&truncated, crafted(4, 0))?;
+			assert_eq!(1, verify(&truncated)?);
+			for (size, compression) in [(u64::MAX, 1), (1 << 62, 0), (1 << 62, 2), (5, 0)] {
+				std::fs::write(&truncated, crafted(size, compression))?;
+				assert!(
+					ArchiveReader::open(&truncated).is_err(),
+					"{} {}",
+					size,
+					compression
+				);
+				assert!(verify(&truncated).is_err());
+			}
+			// decompressing doesn't trust the size either
+			assert!(Compression::Deflate
+				.decompress(b"0123", usize::MAX)
+				.is_err());
+
+			Ok(())
+		}
+	}
+}